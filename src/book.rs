@@ -0,0 +1,109 @@
+use crate::bucket::{bucket_to_pattern, bucketize_answers, parse_result, NUM_BUCKETS};
+use crate::game::GameState;
+use crate::word::{string_to_word, word_to_string, Word};
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+
+// The solver's second guess for every result the opener can produce. The opener is
+// fixed, so these never change for a given setup and can be computed once and saved.
+pub struct OpeningBook {
+    // Identifies the setup the book was built for; see book_key.
+    pub key: String,
+    replies: Vec<Option<Word>>,
+}
+
+// Everything the second guess depends on: the pools, the opener and the search
+// settings. A saved book with a different key is stale.
+pub fn book_key(state: &GameState, guesses: &[Word]) -> String {
+    // FNV-1a over both pools, so a changed dictionary changes the key
+    let mut fingerprint: u64 = 0xcbf29ce484222325;
+    let mut feed = |c: char| fingerprint = (fingerprint ^ c as u64).wrapping_mul(0x100000001b3);
+    state.answers.iter().flatten().for_each(|&c| feed(c));
+    feed('\n');
+    guesses.iter().flatten().for_each(|&c| feed(c));
+    format!(
+        "first={} strategy={:?} no_grey_reuse={} green_bonus={} answer_bias={} tie_seed={:?} pools={:016x}",
+        word_to_string(&state.pattern),
+        state.strategy,
+        state.no_grey_reuse,
+        state.green_bonus,
+        !state.options.no_answer_bias,
+        state.options.tie_seed,
+        fingerprint
+    )
+}
+
+impl OpeningBook {
+    // Search every nonempty bucket of the opener, exactly as the game would on turn two.
+    // None if Ctrl-C cut a search short: a best-so-far reply would be saved under a
+    // valid key and reused forever.
+    pub fn build(state: &GameState, guesses: &Vec<Word>) -> Option<OpeningBook> {
+        let mut replies = vec![None; NUM_BUCKETS];
+        for (result, subset) in bucketize_answers(&state.answers, state.pattern).iter() {
+            if subset.is_empty() || result == NUM_BUCKETS - 1 {
+                continue;
+            }
+
+            let mut turn = state.clone();
+            turn.options.show_progress = false;
+            turn.trace = false;
+            turn.apply_result(state.pattern, result);
+            match turn.answers.len() {
+                1 => turn.pattern = turn.answers[0],
+                _ => turn.suggest(guesses),
+            }
+            if turn.interrupted {
+                return None;
+            }
+            replies[result] = Some(turn.pattern);
+        }
+
+        Some(OpeningBook {
+            key: book_key(state, guesses),
+            replies,
+        })
+    }
+
+    pub fn reply(&self, result: usize) -> Option<Word> {
+        self.replies[result]
+    }
+
+    // A header line with the key, then one `result guess` line per reply.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = File::create(path)?;
+        writeln!(out, "# {}", self.key)?;
+        for (result, reply) in self.replies.iter().enumerate() {
+            if let Some(reply) = reply {
+                writeln!(
+                    out,
+                    "{} {}",
+                    bucket_to_pattern(result),
+                    word_to_string(reply)
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    // None if there's no saved book, it can't be read, or it was built for a
+    // different setup.
+    pub fn load(path: &str, key: &str) -> Option<OpeningBook> {
+        let file = File::open(path).ok()?;
+        let mut lines = io::BufReader::new(file).lines();
+        let header = lines.next()?.ok()?;
+        if header.strip_prefix("# ")? != key {
+            return None;
+        }
+
+        let mut replies = vec![None; NUM_BUCKETS];
+        for line in lines {
+            let line = line.ok()?;
+            let (result, reply) = line.split_once(' ')?;
+            replies[parse_result(result).ok()?] = Some(string_to_word(reply).ok()?);
+        }
+        Some(OpeningBook {
+            key: key.to_string(),
+            replies,
+        })
+    }
+}
@@ -0,0 +1,433 @@
+use crate::word::{Word, ALPHABET, MAX_ALPHABET, WORD_LEN};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const NUM_BUCKETS: usize = usize::pow(3, WORD_LEN as u32); // 5 letters, 3 possibilities
+
+// Past this many buckets (words over 7 letters), per-bucket arrays are too big to
+// allocate for every pattern scored, and Buckets keeps only the nonempty ones.
+const MAX_DENSE_BUCKETS: usize = 1 << 12;
+pub const SPARSE_BUCKETS: bool = NUM_BUCKETS > MAX_DENSE_BUCKETS;
+
+// A value for each bucket: the answers in it, or how many there are. Stored densely
+// by bucket, or sparsely in a map when SPARSE_BUCKETS, where unset buckets read as
+// empty.
+#[derive(Clone, Debug)]
+pub enum Buckets<T> {
+    Dense(Vec<T>),
+    Sparse(HashMap<usize, T>, T),
+}
+
+impl<T: Clone + Default> Buckets<T> {
+    pub fn new() -> Buckets<T> {
+        Buckets::with_sparse(SPARSE_BUCKETS)
+    }
+
+    pub(crate) fn with_sparse(sparse: bool) -> Buckets<T> {
+        if sparse {
+            Buckets::Sparse(HashMap::new(), T::default())
+        } else {
+            Buckets::Dense(vec![T::default(); NUM_BUCKETS])
+        }
+    }
+}
+
+impl<T: Clone + Default> Default for Buckets<T> {
+    fn default() -> Buckets<T> {
+        Buckets::new()
+    }
+}
+
+impl<T> Buckets<T> {
+    // Every bucket with its value, in bucket order. Sparse buckets only list the
+    // ones that were set.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (usize, &T)> + '_> {
+        match self {
+            Buckets::Dense(values) => Box::new(values.iter().enumerate()),
+            Buckets::Sparse(values, _) => {
+                let mut set: Vec<(usize, &T)> = values
+                    .iter()
+                    .map(|(&bucket, value)| (bucket, value))
+                    .collect();
+                set.sort_unstable_by_key(|&(bucket, _)| bucket);
+                Box::new(set.into_iter())
+            }
+        }
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<T: Clone> Buckets<T> {
+    // The values as a slice for scoring: every bucket when dense, only the set ones
+    // (in bucket order) when sparse.
+    pub fn as_slice(&self) -> Cow<'_, [T]> {
+        match self {
+            Buckets::Dense(values) => Cow::Borrowed(values),
+            Buckets::Sparse(..) => Cow::Owned(self.values().cloned().collect()),
+        }
+    }
+}
+
+impl<T> Index<usize> for Buckets<T> {
+    type Output = T;
+
+    fn index(&self, bucket: usize) -> &T {
+        match self {
+            Buckets::Dense(values) => &values[bucket],
+            Buckets::Sparse(values, empty) => values.get(&bucket).unwrap_or(empty),
+        }
+    }
+}
+
+impl<T: Clone> IndexMut<usize> for Buckets<T> {
+    fn index_mut(&mut self, bucket: usize) -> &mut T {
+        match self {
+            Buckets::Dense(values) => &mut values[bucket],
+            Buckets::Sparse(values, empty) => values.entry(bucket).or_insert_with(|| empty.clone()),
+        }
+    }
+}
+
+// How many times a pattern has been scored against an answer, for --stats. Atomic so
+// scoring can run on several threads. get_bucket_in is too hot to count itself, so
+// its callers count in bulk.
+static BUCKET_EVALUATIONS: AtomicU64 = AtomicU64::new(0);
+
+pub fn bucket_evaluations() -> u64 {
+    BUCKET_EVALUATIONS.load(Ordering::Relaxed)
+}
+
+pub(crate) fn count_evaluations(n: usize) {
+    BUCKET_EVALUATIONS.fetch_add(n as u64, Ordering::Relaxed);
+}
+
+// Given a word and a pattern, find out which "information bucket" the pattern would match the word into.
+// Each character position yields a trit, forming a trinary bucket index.
+pub fn get_bucket(pattern: Word, answer: Word) -> usize {
+    count_evaluations(1);
+    get_bucket_in(&ALPHABET.read().unwrap(), pattern, answer)
+}
+
+// Each letter's position in the alphabet, or None if one isn't in it.
+fn letter_indices(alphabet: &[char], word: &Word) -> Option<[usize; WORD_LEN]> {
+    let mut indices = [0; WORD_LEN];
+    for (c, index) in word.iter().zip(indices.iter_mut()) {
+        *index = alphabet.binary_search(c).ok()?;
+    }
+    Some(indices)
+}
+
+// get_bucket against an already-locked alphabet, for use in hot loops.
+pub(crate) fn get_bucket_in(alphabet: &[char], pattern: Word, answer: Word) -> usize {
+    let (Some(pattern_letters), Some(answer_letters)) = (
+        letter_indices(alphabet, &pattern),
+        letter_indices(alphabet, &answer),
+    ) else {
+        return get_bucket_unindexed(pattern, answer);
+    };
+
+    // Only letters of the answer not already matched in place can match out of place,
+    // so greens have to claim their letters before any yellows are handed out.
+    // Otherwise guessing "geese" against "those" marks the first 'e' yellow and
+    // leaves nothing for the green 'e' at the end.
+    let mut letters = [0u8; MAX_ALPHABET];
+    for i in 0..WORD_LEN {
+        if pattern[i] != answer[i] {
+            letters[answer_letters[i]] += 1;
+        }
+    }
+
+    let mut trits = [0; WORD_LEN];
+    for i in 0..WORD_LEN {
+        if pattern[i] == answer[i] {
+            trits[i] = 2; // Match-in-place: 2
+        } else if letters[pattern_letters[i]] > 0 {
+            trits[i] = 1; // Match-out-of-place: 1
+            letters[pattern_letters[i]] -= 1;
+        } // No match: 0
+    }
+
+    encode_bucket(trits)
+}
+
+// get_bucket for words with letters outside the alphabet (no dictionary spelled
+// them, or none was loaded), which can't use the count array. Same rules, with
+// the answer's unmatched letters kept in a list instead.
+fn get_bucket_unindexed(pattern: Word, answer: Word) -> usize {
+    let mut unmatched: Vec<char> = pattern
+        .iter()
+        .zip(answer.iter())
+        .filter(|(p, w)| p != w)
+        .map(|(_, &w)| w)
+        .collect();
+
+    let mut trits = [0; WORD_LEN];
+    for ((p, w), trit) in pattern.iter().zip(answer.iter()).zip(trits.iter_mut()) {
+        if p == w {
+            *trit = 2;
+        } else if let Some(i) = unmatched.iter().position(|c| c == p) {
+            *trit = 1;
+            unmatched.swap_remove(i);
+        }
+    }
+
+    encode_bucket(trits)
+}
+
+// The bucket encoding every other function goes through: one trit per position,
+// with the first letter's trit most significant. So "+-..." is 2*81 + 1*27 = 189.
+pub fn encode_bucket(trits: [usize; WORD_LEN]) -> usize {
+    trits.iter().fold(0, |bucket, &trit| bucket * 3 + trit)
+}
+
+// Inverse of encode_bucket: split a bucket back into one trit per position.
+pub fn decode_bucket(mut bucket: usize) -> [usize; WORD_LEN] {
+    let mut trits = [0; WORD_LEN];
+    for trit in trits.iter_mut().rev() {
+        *trit = bucket % 3;
+        bucket /= 3;
+    }
+    trits
+}
+
+// Render a bucket as its result string (+/-/.).
+pub fn bucket_to_pattern(bucket: usize) -> String {
+    decode_bucket(bucket)
+        .iter()
+        .map(|trit| match trit {
+            2 => '+',
+            1 => '-',
+            _ => '.',
+        })
+        .collect()
+}
+
+pub fn bucketize_answers(answers: &Vec<Word>, pattern: Word) -> Buckets<Vec<Word>> {
+    bucketize_into(Buckets::new(), answers, pattern)
+}
+
+fn bucketize_into(
+    mut buckets: Buckets<Vec<Word>>,
+    answers: &Vec<Word>,
+    pattern: Word,
+) -> Buckets<Vec<Word>> {
+    count_evaluations(answers.len());
+    let alphabet = ALPHABET.read().unwrap();
+    for &answer in answers {
+        let bucket = get_bucket_in(&alphabet, pattern, answer);
+        buckets[bucket].push(answer);
+    }
+    buckets
+}
+
+pub fn bucket_counts(answers: &Vec<Word>, pattern: Word) -> Buckets<usize> {
+    count_into(Buckets::new(), answers, pattern)
+}
+
+fn count_into(mut counts: Buckets<usize>, answers: &Vec<Word>, pattern: Word) -> Buckets<usize> {
+    count_evaluations(answers.len());
+    let alphabet = ALPHABET.read().unwrap();
+    for &answer in answers {
+        let bucket = get_bucket_in(&alphabet, pattern, answer);
+        counts[bucket] += 1;
+    }
+    counts
+}
+
+// The number of distinct results a pattern can produce: a cheap proxy for how much
+// it tells us, since more partitions generally means more information.
+pub fn partition_count(answers: &Vec<Word>, pattern: Word) -> usize {
+    bucket_counts(answers, pattern)
+        .values()
+        .filter(|&&count| count > 0)
+        .count()
+}
+
+// Render a bucket as a row of Wordle's share grid.
+pub fn bucket_to_emoji(bucket: usize) -> String {
+    decode_bucket(bucket)
+        .iter()
+        .map(|trit| match trit {
+            2 => '🟩',
+            1 => '🟨',
+            _ => '⬛',
+        })
+        .collect()
+}
+
+// How many answers are expected to survive a guess that splits them into these
+// buckets, if the answer is equally likely to be any of them: sum(count^2) / total.
+pub fn expected_remaining(counts: &Buckets<usize>) -> f64 {
+    let total: usize = counts.values().sum();
+    let squares: usize = counts.values().map(|&count| count * count).sum();
+    squares as f64 / total as f64
+}
+
+// Parse a result string into its bucket: +/-/. or the squares of a share grid,
+// including the high-contrast orange and blue and light mode's white.
+pub fn parse_result(s: &str) -> Result<usize, String> {
+    let symbols = s.chars().count();
+    if symbols != WORD_LEN {
+        return Err(format!("expected {} symbols, got {}", WORD_LEN, symbols));
+    }
+
+    let mut trits = [0; WORD_LEN];
+    for (c, trit) in s.chars().zip(trits.iter_mut()) {
+        *trit = match c {
+            '+' | '🟩' | '🟧' => 2, // Match-in-place: 2
+            '-' | '🟨' | '🟦' => 1, // Match-out-of-place: 1
+            '.' | '⬛' | '⬜' => 0, // No match: 0
+            _ => return Err(format!("Invalid character: {}", c)),
+        };
+    }
+    Ok(encode_bucket(trits))
+}
+
+// The results in a game's share text, in order. Lines without squares (the
+// "Wordle 1,234 4/6" header, blank lines, links) are skipped.
+pub fn parse_share_grid(text: &str) -> Result<Vec<usize>, String> {
+    let is_square = |c: char| "🟩🟧🟨🟦⬛⬜".contains(c);
+    let results = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.chars().any(is_square))
+        .map(|line| parse_result(line).map_err(|message| format!("{}: {}", line, message)))
+        .collect::<Result<Vec<usize>, String>>()?;
+    if results.is_empty() {
+        return Err("no share grid found".to_string());
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixture, word};
+
+    #[test]
+    fn first_letter_is_the_most_significant_trit() {
+        assert_eq!(encode_bucket([2, 1, 0, 0, 0]), 2 * 81 + 27);
+        assert_eq!(decode_bucket(2 * 81 + 27), [2, 1, 0, 0, 0]);
+        assert_eq!(encode_bucket([2; WORD_LEN]), NUM_BUCKETS - 1);
+    }
+
+    #[test]
+    fn get_bucket_matches_hand_traced_results() {
+        // c is in place; r, a, n and e are all elsewhere in caner: +----
+        assert_eq!(get_bucket(word("crane"), word("caner")), 202);
+        // Only those's s and final e are matched: ...++
+        assert_eq!(get_bucket(word("geese"), word("those")), 8);
+        assert_eq!(get_bucket(word("crane"), word("crane")), NUM_BUCKETS - 1);
+    }
+
+    #[test]
+    fn letters_outside_the_alphabet_match_the_same_way() {
+        let answers = fixture("duplicates.txt");
+        for &pattern in &answers {
+            for &answer in &answers {
+                assert_eq!(
+                    get_bucket_unindexed(pattern, answer),
+                    get_bucket(pattern, answer)
+                );
+            }
+        }
+
+        // No dictionary in these tests spells Cyrillic
+        let pattern = bucket_to_pattern(get_bucket(word("ёжики"), word("ёлкии")));
+        assert_eq!(pattern, "+.--+");
+    }
+
+    // Survivors of `guess` scoring `result` among the duplicate-letter fixture.
+    fn survivors(guess: &str, result: &str) -> Vec<Word> {
+        let answers = fixture("duplicates.txt");
+        bucketize_answers(&answers, word(guess))[parse_result(result).unwrap()].clone()
+    }
+
+    #[test]
+    fn greens_claim_letters_before_yellows() {
+        // An out-of-place 'e' before the in-place one must not use up those's only 'e'
+        assert_eq!(
+            bucket_to_pattern(get_bucket(word("geese"), word("those"))),
+            "...++"
+        );
+        assert_eq!(survivors("geese", "...++"), vec![word("those")]);
+    }
+
+    #[test]
+    fn single_reveal_keeps_double_letter_answers() {
+        // bevel reveals two e's against belle, one in place and one elsewhere
+        assert_eq!(
+            bucket_to_pattern(get_bucket(word("bevel"), word("belle"))),
+            "++.--"
+        );
+        assert!(survivors("bevel", "++.--").contains(&word("belle")));
+        // A guess with one 'l' says nothing about belle's second one
+        assert_eq!(
+            bucket_to_pattern(get_bucket(word("lemon"), word("belle"))),
+            "-+..."
+        );
+        assert!(survivors("lemon", "-+...").contains(&word("belle")));
+    }
+
+    fn count_e(word: &Word) -> usize {
+        word.iter().filter(|&&c| c == 'e').count()
+    }
+
+    #[test]
+    fn grey_copies_cap_the_letter_count() {
+        let answers = fixture("triples.txt");
+        let buckets = bucketize_answers(&answers, word("eerie"));
+
+        // One 'e' matched and two grey: exactly one 'e', so two- and three-'e' words go
+        let single = &buckets[parse_result("....+").unwrap()];
+        assert_eq!(single, &vec![word("those"), word("shone"), word("stole")]);
+        assert!(single.iter().all(|answer| count_e(answer) == 1));
+
+        // Three copies matched: at least three, and nothing says there are more
+        let triple = &buckets[parse_result("-+..+").unwrap()];
+        assert_eq!(triple, &vec![word("geese"), word("tepee")]);
+        assert!(triple.iter().all(|answer| count_e(answer) == 3));
+    }
+
+    #[test]
+    fn parse_result_and_bucket_to_pattern_agree() {
+        assert_eq!(parse_result("+----"), Ok(202));
+        assert_eq!(bucket_to_pattern(202), "+----");
+        assert_eq!(parse_result("...++"), Ok(8));
+        for bucket in 0..NUM_BUCKETS {
+            assert_eq!(parse_result(&bucket_to_pattern(bucket)), Ok(bucket));
+            assert_eq!(encode_bucket(decode_bucket(bucket)), bucket);
+        }
+    }
+
+    #[test]
+    fn sparse_buckets_match_dense() {
+        let answers = fixture("duplicates.txt");
+        for &pattern in &answers {
+            let dense = bucketize_into(Buckets::with_sparse(false), &answers, pattern);
+            let sparse = bucketize_into(Buckets::with_sparse(true), &answers, pattern);
+            let nonempty: Vec<(usize, &Vec<Word>)> = dense
+                .iter()
+                .filter(|(_, subset)| !subset.is_empty())
+                .collect();
+            assert_eq!(sparse.iter().collect::<Vec<_>>(), nonempty);
+            assert_eq!(sparse[0], dense[0]);
+
+            let dense = count_into(Buckets::with_sparse(false), &answers, pattern);
+            let sparse = count_into(Buckets::with_sparse(true), &answers, pattern);
+            assert_eq!(expected_remaining(&sparse), expected_remaining(&dense));
+            assert_eq!(sparse[NUM_BUCKETS - 1], dense[NUM_BUCKETS - 1]);
+        }
+    }
+
+    #[test]
+    fn parse_result_rejects_bad_input() {
+        assert!(parse_result("+--").is_err());
+        assert!(parse_result("+---x").is_err());
+    }
+}
@@ -0,0 +1,529 @@
+use crate::bucket::{
+    bucket_counts, bucket_to_emoji, bucketize_answers, decode_bucket, get_bucket, partition_count,
+    NUM_BUCKETS,
+};
+use crate::strategy::{
+    best_patterns, interruptible, rank_by_partitions, score_all, SearchOptions, Strategy,
+};
+use crate::word::{alphabet, word_to_string, Word, WORD_LEN};
+use std::collections::{BTreeSet, HashMap};
+use std::time::Instant;
+
+// What the results so far tell us about the answer's letters.
+#[derive(Clone, Default)]
+pub struct Constraints {
+    pub greens: [Option<char>; WORD_LEN],
+    pub present: BTreeSet<char>,
+    pub absent: BTreeSet<char>,
+    pub results: Vec<(Word, usize)>,
+}
+
+impl Constraints {
+    pub fn add_result(&mut self, pattern: Word, result: usize) {
+        self.results.push((pattern, result));
+        let trits = decode_bucket(result);
+
+        for (i, (&c, &trit)) in pattern.iter().zip(trits.iter()).enumerate() {
+            if trit == 2 {
+                self.greens[i] = Some(c);
+            }
+            if trit > 0 {
+                self.present.insert(c);
+                self.absent.remove(&c);
+            }
+        }
+
+        // A grey letter is only absent if no other copy of it matched; otherwise
+        // it just means the answer has no more copies.
+        for (&c, &trit) in pattern.iter().zip(trits.iter()) {
+            if trit == 0 && !self.present.contains(&c) {
+                self.absent.insert(c);
+            }
+        }
+    }
+
+    // Clues in a new result that can't be squared with the results so far, described
+    // for the user. Any of them means no answer can be left.
+    pub fn conflicts(&self, pattern: Word, result: usize) -> Vec<String> {
+        let trits = decode_bucket(result);
+        let mut conflicts = Vec::new();
+
+        for (i, (&c, &trit)) in pattern.iter().zip(trits.iter()).enumerate() {
+            let position = i + 1;
+            if trit > 0 && self.absent.contains(&c) {
+                let now = if trit == 2 { "green" } else { "yellow" };
+                conflicts.push(format!("you marked {:?} absent earlier but now {}", c, now));
+            }
+            match self.greens[i] {
+                Some(green) if green == c && trit != 2 => conflicts.push(format!(
+                    "{:?} was green in position {} earlier but now isn't",
+                    c, position
+                )),
+                Some(green) if green != c && trit == 2 => conflicts.push(format!(
+                    "position {} was green {:?} earlier but now {:?} is",
+                    position, green, c
+                )),
+                _ => {}
+            }
+        }
+
+        // A grey letter with no other copy matched in the same guess means the
+        // answer has none at all
+        for (&c, &trit) in pattern.iter().zip(trits.iter()) {
+            let matched = pattern
+                .iter()
+                .zip(trits.iter())
+                .any(|(&other, &other_trit)| other == c && other_trit > 0);
+            let message = format!("you marked {:?} present earlier but now absent", c);
+            if trit == 0 && !matched && self.present.contains(&c) && !conflicts.contains(&message) {
+                conflicts.push(message);
+            }
+        }
+        conflicts
+    }
+
+    // How many individual letter clues a word contradicts, summed over every result
+    // so far. Zero means the word is still a candidate.
+    pub fn violations(&self, word: Word) -> usize {
+        self.results
+            .iter()
+            .map(|&(pattern, result)| {
+                let expected = decode_bucket(result);
+                let actual = decode_bucket(get_bucket(pattern, word));
+                expected
+                    .iter()
+                    .zip(actual.iter())
+                    .filter(|(e, a)| e != a)
+                    .count()
+            })
+            .sum()
+    }
+
+    // How many of `guess`'s letters are known present but unplaced, in a position
+    // no earlier guess has tried them in: each could turn a yellow into a green.
+    pub fn placement_tests(&self, guess: Word) -> usize {
+        guess
+            .iter()
+            .enumerate()
+            .filter(|&(i, c)| {
+                self.present.contains(c)
+                    && self.greens[i].is_none()
+                    && !self.results.iter().any(|(pattern, _)| pattern[i] == *c)
+            })
+            .count()
+    }
+
+    // e.g. "c _ a _ e | present: a c e | absent: i r s"
+    pub fn summary(&self) -> String {
+        let greens: Vec<String> = self
+            .greens
+            .iter()
+            .map(|green| green.map_or("_".to_string(), |c| c.to_string()))
+            .collect();
+        let join = |letters: &BTreeSet<char>| {
+            letters
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        format!(
+            "{} | present: {} | absent: {}",
+            greens.join(" "),
+            join(&self.present),
+            join(&self.absent)
+        )
+    }
+}
+
+// The `count` words that violate the fewest clues, fewest first. Unlike filtering,
+// this keeps words that would match if a result had been entered slightly wrong.
+pub fn near_misses(constraints: &Constraints, words: &[Word], count: usize) -> Vec<(Word, usize)> {
+    let mut scored: Vec<(Word, usize)> = words
+        .iter()
+        .map(|&word| (word, constraints.violations(word)))
+        .collect();
+    scored.sort_by_key(|&(word, violations)| (violations, word));
+    scored.truncate(count);
+    scored
+}
+
+// How far from the best score (relative) a guess can be and still count as tied
+// for --green-bonus.
+const NEAR_TIE: f64 = 0.01;
+
+// How many of the best guesses --trace shows each turn.
+const TRACE_TOP: usize = 3;
+
+// With this few candidates left we're in the endgame: check for probe words and
+// show the chances of winning soon.
+pub const ENDGAME: usize = 20;
+
+// Whether playing `pattern` would tell every candidate apart.
+fn separates(answers: &Vec<Word>, pattern: Word) -> bool {
+    bucket_counts(answers, pattern)
+        .values()
+        .all(|&count| count <= 1)
+}
+
+// What the on-screen keyboard would show for a letter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LetterStatus {
+    Present,
+    Absent,
+    Unknown,
+}
+
+// Everything we know about the game in progress.
+#[derive(Clone)]
+pub struct GameState {
+    pub answers: Vec<Word>,
+    pub pattern: Word,
+    pub strategy: Strategy,
+    pub options: SearchOptions,
+    pub constraints: Constraints,
+    // Never suggest guesses that use a letter already known to be absent.
+    pub no_grey_reuse: bool,
+    // Among near-tied guesses, prefer the one testing the most yellows in new places.
+    pub green_bonus: bool,
+    // Print diagnostics about each turn's search.
+    pub trace: bool,
+    // Whether Ctrl-C cut the last suggest short, leaving the best pattern so far.
+    pub interrupted: bool,
+}
+
+impl GameState {
+    pub fn new(
+        answers: Vec<Word>,
+        first: Word,
+        strategy: Strategy,
+        options: SearchOptions,
+    ) -> GameState {
+        GameState {
+            answers,
+            pattern: first,
+            strategy,
+            options: SearchOptions {
+                show_progress: true,
+                ..options
+            },
+            constraints: Constraints::default(),
+            no_grey_reuse: false,
+            green_bonus: false,
+            trace: false,
+            interrupted: false,
+        }
+    }
+
+    // The word that was just solved, if `result` is all green for the current
+    // pattern. Checked before filtering: the pattern is the answer whether or not it's
+    // in the answer list, and filtering would leave no candidates if it isn't.
+    pub fn solved_by(&self, result: usize) -> Option<Word> {
+        (result == NUM_BUCKETS - 1).then_some(self.pattern)
+    }
+
+    // Narrow the candidates to those consistent with `pattern` scoring `result`.
+    // Returns the result's conflicts with earlier ones, if any, to warn about.
+    pub fn apply_result(&mut self, pattern: Word, result: usize) -> Vec<String> {
+        let conflicts = self.constraints.conflicts(pattern, result);
+        let buckets = bucketize_answers(&self.answers, pattern);
+        self.answers = buckets[result].clone();
+        self.constraints.add_result(pattern, result);
+        conflicts
+    }
+
+    // The keyboard state for every letter in the alphabet, for front-ends to render.
+    // Besides explicit clues, this uses the remaining candidates: a letter none of
+    // them contain is dead even if it was never marked grey, and a letter all of
+    // them contain is certainly present.
+    pub fn keyboard_state(&self) -> HashMap<char, LetterStatus> {
+        alphabet()
+            .into_iter()
+            .map(|c| {
+                let in_candidates = self
+                    .answers
+                    .iter()
+                    .filter(|answer| answer.contains(&c))
+                    .count();
+                let status = if self.constraints.present.contains(&c)
+                    || (!self.answers.is_empty() && in_candidates == self.answers.len())
+                {
+                    LetterStatus::Present
+                } else if self.constraints.absent.contains(&c) || in_candidates == 0 {
+                    LetterStatus::Absent
+                } else {
+                    LetterStatus::Unknown
+                };
+                (c, status)
+            })
+            .collect()
+    }
+
+    // Endgames like _ight (fight, light, might, night, sight) where no candidate
+    // can tell the rest apart: the guess from the full pool that splits them
+    // most, if it beats the suggested pattern and every candidate. With more
+    // candidates than a guess has letters to test, as in _atch, no word splits
+    // them all, but a probe still leaves the fewest together.
+    pub fn probe_word(&self, guesses: &[Word]) -> Option<Word> {
+        if self.answers.len() > ENDGAME
+            || self
+                .answers
+                .iter()
+                .any(|&answer| separates(&self.answers, answer))
+        {
+            return None;
+        }
+        let to_beat = self
+            .answers
+            .iter()
+            .chain([&self.pattern])
+            .map(|&guess| partition_count(&self.answers, guess))
+            .max()
+            .unwrap();
+        let &(probe, partitions) = rank_by_partitions(&self.answers, guesses).first()?;
+        (partitions > to_beat).then_some(probe)
+    }
+
+    // The chance that the pattern wins outright, and the chance its result leaves
+    // exactly one candidate to win with on the turn after.
+    pub fn win_chances(&self) -> (f64, f64) {
+        let counts = bucket_counts(&self.answers, self.pattern);
+        let total = self.answers.len() as f64;
+        let singletons = counts
+            .iter()
+            .filter(|&(bucket, &count)| bucket != NUM_BUCKETS - 1 && count == 1)
+            .count();
+        (
+            counts[NUM_BUCKETS - 1] as f64 / total,
+            singletons as f64 / total,
+        )
+    }
+
+    // Wordle's share grid for this game, ending with the winning guess: every result
+    // so far, then an all-green row for the pattern about to be (or just) played.
+    pub fn share_grid(&self) -> String {
+        let turns = self.constraints.results.len() + 1;
+        let mut grid = format!("Solver {}/6", turns);
+        for &(_, result) in &self.constraints.results {
+            grid.push('\n');
+            grid.push_str(&bucket_to_emoji(result));
+        }
+        grid.push('\n');
+        grid.push_str(&bucket_to_emoji(NUM_BUCKETS - 1));
+        grid
+    }
+
+    // The guess testing the most placements among those scoring within NEAR_TIE of
+    // the best, keeping the better score when that ties too.
+    fn best_for_placement(&self, guesses: &[Word]) -> Word {
+        let scored = score_all(&self.answers, guesses, self.strategy, self.options);
+        let best = scored[0].1;
+        let tolerance = NEAR_TIE * best.abs().max(1.0);
+
+        let mut choice = scored[0].0;
+        let mut most = self.constraints.placement_tests(choice);
+        for &(guess, _) in scored
+            .iter()
+            .take_while(|&&(_, score)| score - best <= tolerance)
+        {
+            let tests = self.constraints.placement_tests(guess);
+            if tests > most {
+                choice = guess;
+                most = tests;
+            }
+        }
+        choice
+    }
+
+    // Pick the next pattern to play using the active strategy.
+    // The search can be cut short with Ctrl-C.
+    pub fn suggest(&mut self, guesses: &Vec<Word>) {
+        let allowed: Vec<Word>;
+        let mut guesses = guesses;
+        if self.no_grey_reuse {
+            let absent = &self.constraints.absent;
+            allowed = guesses
+                .iter()
+                .filter(|guess| !guess.iter().any(|c| absent.contains(c)))
+                .copied()
+                .collect();
+            // Candidates never contain grey letters, but they might not be valid
+            // guesses; fall back to the full pool rather than having nothing.
+            if !allowed.is_empty() {
+                guesses = &allowed;
+            }
+        }
+
+        let start = Instant::now();
+        (self.pattern, self.interrupted) = interruptible(|| {
+            if self.green_bonus && self.strategy != Strategy::Rollout {
+                self.best_for_placement(guesses)
+            } else {
+                self.strategy
+                    .choose_pattern(&self.answers, guesses, self.options)
+            }
+        });
+        if self.interrupted {
+            eprintln!("Search interrupted; using best pattern so far");
+        }
+
+        if self.trace {
+            let elapsed = start.elapsed();
+            let top: Vec<String> = best_patterns(
+                &self.answers,
+                guesses,
+                self.strategy,
+                self.options,
+                TRACE_TOP,
+            )
+            .iter()
+            .map(|(guess, score)| format!("{} ({:.3})", word_to_string(guess), score))
+            .collect();
+            println!("[trace] {} candidates", self.answers.len());
+            println!(
+                "[trace] scored {} guesses in {:.2?}",
+                guesses.len(),
+                elapsed
+            );
+            println!("[trace] top: {}", top.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bucket::{bucket_to_pattern, parse_result};
+    use crate::testing::{fixture, word, words};
+
+    // Constraints after playing `guess` and getting `result`, e.g. "+-..."
+    fn after(guess: &str, result: &str) -> Constraints {
+        let mut constraints = Constraints::default();
+        constraints.add_result(word(guess), parse_result(result).unwrap());
+        constraints
+    }
+
+    fn conflicts(constraints: &Constraints, guess: &str, result: &str) -> Vec<String> {
+        constraints.conflicts(word(guess), parse_result(result).unwrap())
+    }
+
+    #[test]
+    fn absent_letters_cant_match_later() {
+        assert_eq!(
+            conflicts(&after("crane", "....."), "crane", "+...."),
+            vec!["you marked 'c' absent earlier but now green"]
+        );
+        assert_eq!(
+            conflicts(&after("crane", "....."), "slate", "..-.."),
+            vec!["you marked 'a' absent earlier but now yellow"]
+        );
+    }
+
+    #[test]
+    fn greens_cant_move_or_go_away() {
+        assert_eq!(
+            conflicts(&after("crane", "+...."), "crane", "-...."),
+            vec!["'c' was green in position 1 earlier but now isn't"]
+        );
+        assert_eq!(
+            conflicts(&after("crane", "+...."), "slate", "+...."),
+            vec!["position 1 was green 'c' earlier but now 's' is"]
+        );
+    }
+
+    #[test]
+    fn present_letters_cant_be_absent_later() {
+        assert_eq!(
+            conflicts(&after("crane", "....-"), "slate", "....."),
+            vec!["you marked 'e' present earlier but now absent"]
+        );
+    }
+
+    #[test]
+    fn a_grey_copy_next_to_a_matched_one_is_not_a_conflict() {
+        // speed against abbey: the first e is grey because abbey has only one
+        let mut constraints = Constraints::default();
+        constraints.add_result(word("crane"), get_bucket(word("crane"), word("abbey")));
+        let result = get_bucket(word("speed"), word("abbey"));
+        assert_eq!(bucket_to_pattern(result), "...+.");
+        assert!(constraints.conflicts(word("speed"), result).is_empty());
+    }
+
+    #[test]
+    fn all_green_solves_a_guess_outside_the_answers() {
+        let mut state = GameState::new(
+            words(&["crane", "slate"]),
+            word("trace"),
+            Strategy::default(),
+            SearchOptions::default(),
+        );
+        let result = get_bucket(word("trace"), word("trace"));
+        assert_eq!(state.solved_by(result), Some(word("trace")));
+
+        // Filtering alone would have found no candidates at all
+        state.apply_result(state.pattern, result);
+        assert!(state.answers.is_empty());
+    }
+
+    #[test]
+    fn other_results_are_not_solved() {
+        let state = GameState::new(
+            words(&["crane", "slate"]),
+            word("trace"),
+            Strategy::default(),
+            SearchOptions::default(),
+        );
+        assert_eq!(
+            state.solved_by(get_bucket(word("trace"), word("crane"))),
+            None
+        );
+    }
+
+    #[test]
+    fn keyboard_state_uses_the_remaining_candidates() {
+        let mut state = GameState::new(
+            fixture("split.txt"),
+            word("crane"),
+            Strategy::default(),
+            SearchOptions::default(),
+        );
+        state.apply_result(word("crane"), get_bucket(word("crane"), word("crate")));
+        assert_eq!(state.answers, words(&["crate"]));
+
+        let keyboard = state.keyboard_state();
+        assert_eq!(keyboard[&'n'], LetterStatus::Absent);
+        // Only pound and grace had these, and neither was ever guessed
+        assert!(!state.constraints.absent.contains(&'g'));
+        assert_eq!(keyboard[&'g'], LetterStatus::Absent);
+        assert_eq!(keyboard[&'p'], LetterStatus::Absent);
+        // Every candidate has t, though no guess has shown it yet
+        assert_eq!(keyboard[&'t'], LetterStatus::Present);
+    }
+
+    #[test]
+    fn clusters_no_candidate_splits_get_a_probe() {
+        // Each _atch answer only splits off itself; blimp tests four first letters
+        let answers = fixture("cluster.txt");
+        let state = GameState::new(
+            answers.clone(),
+            word("batch"),
+            Strategy::default(),
+            SearchOptions::default(),
+        );
+        let mut guesses = answers;
+        guesses.extend(words(&["clamp", "blimp", "fight"]));
+        assert_eq!(state.probe_word(&guesses), Some(word("blimp")));
+    }
+
+    #[test]
+    fn no_probe_when_a_candidate_splits_the_rest() {
+        let state = GameState::new(
+            words(&["crane", "slate", "trace"]),
+            word("crane"),
+            Strategy::default(),
+            SearchOptions::default(),
+        );
+        assert_eq!(state.probe_word(&fixture("cluster.txt")), None);
+    }
+}
@@ -0,0 +1,12 @@
+// Core solver: dictionaries, result buckets, guess scoring and game state.
+// The `wordle-bot` binary is a command-line front-end over this crate.
+
+pub mod book;
+pub mod bucket;
+pub mod game;
+pub mod solve;
+pub mod strategy;
+pub mod table;
+#[cfg(test)]
+mod testing;
+pub mod word;
@@ -1,149 +1,1419 @@
 use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
-use hashbag::HashBag;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wordle_bot::book::{book_key, OpeningBook};
+use wordle_bot::bucket::{
+    bucket_counts, bucket_evaluations, bucket_to_emoji, bucket_to_pattern, bucketize_answers,
+    expected_remaining, get_bucket, parse_result, parse_share_grid, partition_count, NUM_BUCKETS,
+};
+use wordle_bot::game::{near_misses, Constraints, GameState, ENDGAME};
+use wordle_bot::solve::{
+    adversarial_guesses, autosolve, benchmark, build_tree, compute_opener, default_opener,
+    lower_bound, tree_paths, write_tree_json, write_tree_table,
+};
+use wordle_bot::strategy::{
+    best_patterns, install_interrupt_handler, interruptible, pattern_score, rank_by_partitions,
+    score_all, SearchOptions, Strategy,
+};
+use wordle_bot::table::{precompute_table, DEFAULT_MAX_TABLE_BYTES};
+use wordle_bot::word::{
+    read_pools, string_to_word, unknown_char, word_to_string, PoolMode, Word,
+    DEFAULT_COMMENT_PREFIX, DEFAULT_DICTIONARY, WORD_LEN,
+};
 
-const WORD_LEN: usize = 5;
-const NUM_BUCKETS: usize = usize::pow(3, WORD_LEN as u32) as usize; // 5 letters, 3 possibilities
+// Autosolve each answer in an archive of `date<TAB>word` lines, writing a CSV of
+// `date,word,guesses` so the difficulty of each day can be plotted.
+fn export_archive_curve(
+    archive_path: &str,
+    out: &mut dyn Write,
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    first: Word,
+    strategy: Strategy,
+    options: SearchOptions,
+) -> Result<(), String> {
+    let file = File::open(archive_path).map_err(|error| format!("{}: {}", archive_path, error))?;
+    let mut entries: Vec<(String, String)> = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|error| format!("{}: {}", archive_path, error))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (date, word) = line
+            .split_once('\t')
+            .ok_or_else(|| format!("Invalid archive line: {}", line))?;
+        entries.push((date.trim().to_string(), word.trim().to_string()));
+    }
 
-type Word = [char; WORD_LEN]; // Stack-allocated fixed-size word for cache efficiency
+    writeln!(out, "date,word,guesses").unwrap();
+    for (date, word) in entries.iter().progress_with(
+        ProgressBar::new(entries.len() as u64).with_style(
+            ProgressStyle::default_bar()
+                .template("Solving archive: [{elapsed} / {duration}] {wide_bar} {pos}/{len}"),
+        ),
+    ) {
+        let answer = match string_to_word(word) {
+            Ok(answer) => answer,
+            Err(message) => {
+                eprintln!("Skipping {}: {}: {}", date, word, message);
+                continue;
+            }
+        };
+        match autosolve(answer, answers, guesses, first, strategy, options) {
+            Ok(path) => writeln!(out, "{},{},{}", date, word, path.len()).unwrap(),
+            Err(message) => eprintln!("Skipping {}: {}: {}", date, word, message),
+        }
+    }
+    Ok(())
+}
 
-// Optimal first word is always the same
-const FIRST_GUESS: Word = ['r', 'a', 'i', 's', 'e'];
+// Autosolve each answer in a file of one answer per line, writing each game as
+// `answer: guess1 guess2 ... (N)` and a summary line at the end.
+fn solve_batch(
+    batch_path: &str,
+    out: &mut dyn Write,
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    first: Word,
+    strategy: Strategy,
+    options: SearchOptions,
+) -> Result<(), String> {
+    let file = File::open(batch_path).map_err(|error| format!("{}: {}", batch_path, error))?;
+    let mut puzzles: Vec<String> = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|error| format!("{}: {}", batch_path, error))?;
+        if !line.trim().is_empty() {
+            puzzles.push(line.trim().to_string());
+        }
+    }
 
-fn string_to_word(s: &str) -> Word {
-    let mut word: Word = Default::default();
-    for (i, c) in s.chars().enumerate() {
-        word[i] = c;
+    let mut counts = Vec::new();
+    for word in puzzles.iter().progress_with(
+        ProgressBar::new(puzzles.len() as u64).with_style(
+            ProgressStyle::default_bar()
+                .template("Solving batch: [{elapsed} / {duration}] {wide_bar} {pos}/{len}"),
+        ),
+    ) {
+        let answer = match string_to_word(word) {
+            Ok(answer) => answer,
+            Err(message) => {
+                eprintln!("Skipping {}: {}", word, message);
+                continue;
+            }
+        };
+        match autosolve(answer, answers, guesses, first, strategy, options) {
+            Ok(path) => {
+                let path: Vec<String> = path.iter().map(word_to_string).collect();
+                writeln!(out, "{}: {} ({})", word, path.join(" "), path.len()).unwrap();
+                counts.push(path.len());
+            }
+            Err(message) => eprintln!("Skipping {}: {}", word, message),
+        }
     }
-    word
+
+    if let Some(worst) = counts.iter().max() {
+        let average = counts.iter().sum::<usize>() as f64 / counts.len() as f64;
+        writeln!(
+            out,
+            "solved {} of {}: average {:.4} guesses, worst {}",
+            counts.len(),
+            puzzles.len(),
+            average,
+            worst
+        )
+        .unwrap();
+    }
+    Ok(())
+}
+
+// Solve every answer and write the `k` that take the most guesses, with their
+// paths. Ties go to the answer left among more candidates after the opener.
+fn print_hardest(
+    out: &mut dyn Write,
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    first: Word,
+    strategy: Strategy,
+    options: SearchOptions,
+    k: usize,
+) -> Result<(), String> {
+    // One tree covers every answer, so each game state is only searched once
+    let tree = build_tree(answers, guesses, first, strategy, options)?;
+    let after_opener = bucket_counts(answers, first);
+    let mut solved: Vec<(Vec<Word>, usize)> = tree_paths(&tree)
+        .into_iter()
+        .map(|path| {
+            let remaining = after_opener[get_bucket(first, *path.last().unwrap())];
+            (path, remaining)
+        })
+        .collect();
+    solved.sort_by_key(|(path, remaining)| std::cmp::Reverse((path.len(), *remaining)));
+
+    for (path, remaining) in solved.iter().take(k) {
+        let words: Vec<String> = path.iter().map(word_to_string).collect();
+        writeln!(
+            out,
+            "{}: {} ({}, {} left after the opener)",
+            words.last().unwrap(),
+            words.join(" "),
+            path.len(),
+            remaining
+        )
+        .unwrap();
+    }
+    Ok(())
 }
 
-fn word_to_string(word: &Word) -> String {
-    word.iter().collect()
+// Write the answers in each nonempty bucket of `pattern` to their own file in
+// `outdir`. Files are named by result with g/y/x for +/-/. (e.g. `gxyxx.txt`),
+// since `.` and a leading `-` are awkward in filenames.
+fn export_buckets(answers: &Vec<Word>, pattern: Word, outdir: &Path) -> io::Result<()> {
+    fs::create_dir_all(outdir)?;
+
+    for (bucket, subset) in bucketize_answers(answers, pattern).iter() {
+        if subset.is_empty() {
+            continue;
+        }
+
+        let name: String = bucket_to_pattern(bucket)
+            .chars()
+            .map(|c| match c {
+                '+' => 'g',
+                '-' => 'y',
+                _ => 'x',
+            })
+            .collect();
+        let mut file = File::create(outdir.join(format!("{}.txt", name)))?;
+        for answer in subset {
+            writeln!(file, "{}", word_to_string(answer))?;
+        }
+    }
+
+    Ok(())
 }
 
-fn read_words(path: &str) -> Vec<Word> {
-    let file = File::open(path).unwrap();
-    io::BufReader::new(file)
-        .lines()
-        .map(|line| string_to_word(&line.unwrap()))
-        .collect()
+const MAYBE_COUNT: usize = 10;
+
+fn print_near_misses(constraints: &Constraints, words: &[Word]) {
+    for (word, violations) in near_misses(constraints, words, MAYBE_COUNT) {
+        println!("{} ({} violated)", word_to_string(&word), violations);
+    }
 }
 
-// Given a word and a pattern, find out which "information bucket" the pattern would match the word into.
-// Each character position yields a trit, forming a trinary bucket index.
-fn get_bucket(pattern: Word, answer: Word) -> usize {
-    let mut bucket = 0;
-    let mut letters: HashBag<char> = answer.into_iter().collect();
+#[derive(Default)]
+struct Args {
+    archive: Option<String>,
+    out: Option<String>,
+    first: Option<String>,
+    tree: bool,
+    shuffle_ties: bool,
+    seed: Option<u64>,
+    answers: Option<String>,
+    guesses: Option<String>,
+    pool_mode: PoolMode,
+    partitions: Option<String>,
+    top: Option<usize>,
+    no_grey_reuse: bool,
+    green_bonus: bool,
+    trace: bool,
+    guess: Option<String>,
+    result: Option<String>,
+    history: Option<String>,
+    strategy: Strategy,
+    benchmark: bool,
+    benchmark_adversarial: bool,
+    comment_prefix: Option<String>,
+    allow_punctuation: bool,
+    print_guess_only: bool,
+    bucketize: Option<String>,
+    outdir: Option<String>,
+    precompute: bool,
+    practice: bool,
+    stats_file: Option<String>,
+    book: Option<String>,
+    share: bool,
+    list_limit: Option<usize>,
+    lower_bound: bool,
+    alpha: Option<f64>,
+    export_table: Option<String>,
+    batch: Option<String>,
+    cheatsheet: Option<String>,
+    opener_stats: Option<String>,
+    no_answer_bias: bool,
+    forbid_letters: Option<String>,
+    opener_threshold: Option<usize>,
+    exit_code_count: bool,
+    from_clipboard: bool,
+    answer: Option<String>,
+    stats: bool,
+    max_table_mem: Option<u64>,
+    hardest: Option<usize>,
+    confirm: bool,
+}
 
-    for (p, w) in pattern.iter().zip(answer.iter()) {
-        bucket *= 3; // Trinary SHL
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut argv = std::env::args().skip(1);
 
-        if p == w {
-            bucket += 2; // Match-in-place: 2
-            letters.remove(p);
-        } else if letters.contains(p) > 0 {
-            bucket += 1; // Match-out-of-place: 1
-            letters.remove(p);
-        } // No match: 0
+    while let Some(arg) = argv.next() {
+        let mut value = || {
+            argv.next()
+                .ok_or_else(|| format!("{} requires a value", arg))
+        };
+        match arg.as_str() {
+            "--archive" => args.archive = Some(value()?),
+            "--batch" => args.batch = Some(value()?),
+            "--cheatsheet" => args.cheatsheet = Some(value()?),
+            "--opener-stats" => args.opener_stats = Some(value()?),
+            "--no-answer-bias" => args.no_answer_bias = true,
+            "--forbid-letters" => args.forbid_letters = Some(value()?),
+            "--exit-code-count" => args.exit_code_count = true,
+            "--from-clipboard" => args.from_clipboard = true,
+            "--stats" => args.stats = true,
+            "--confirm" => args.confirm = true,
+            "--hardest" => {
+                args.hardest = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "--hardest must be an integer".to_string())?,
+                )
+            }
+            "--max-table-mem" => {
+                args.max_table_mem = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "--max-table-mem must be a number of megabytes".to_string())?,
+                )
+            }
+            "--answer" => args.answer = Some(value()?),
+            "--opener-threshold" => {
+                args.opener_threshold = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "--opener-threshold must be an integer".to_string())?,
+                )
+            }
+            "--out" => args.out = Some(value()?),
+            "--first" => args.first = Some(value()?),
+            "--tree" => args.tree = true,
+            "--export-table" => args.export_table = Some(value()?),
+            "--shuffle-ties" => args.shuffle_ties = true,
+            "--seed" => {
+                args.seed = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "--seed must be an integer".to_string())?,
+                )
+            }
+            "--answers" | "--dict" => args.answers = Some(value()?),
+            "--guesses" => args.guesses = Some(value()?),
+            "--pools" => {
+                let name = value()?;
+                args.pool_mode = PoolMode::from_name(&name)
+                    .ok_or_else(|| format!("Unknown pool mode: {}", name))?;
+            }
+            "--answers-equal-guesses" => args.pool_mode = PoolMode::Equal,
+            "--partitions" => args.partitions = Some(value()?),
+            "--no-grey-reuse" => args.no_grey_reuse = true,
+            "--green-bonus" => args.green_bonus = true,
+            "--trace" => args.trace = true,
+            "--guess" => args.guess = Some(value()?),
+            "--result" => args.result = Some(value()?),
+            "--history" => args.history = Some(value()?),
+            "--strategy" => {
+                let name = value()?;
+                args.strategy = Strategy::from_name(&name)
+                    .ok_or_else(|| format!("Unknown strategy: {}", name))?;
+            }
+            "--benchmark" => args.benchmark = true,
+            "--benchmark-adversarial" => args.benchmark_adversarial = true,
+            "--comment-prefix" => args.comment_prefix = Some(value()?),
+            "--allow-punctuation" => args.allow_punctuation = true,
+            "--print-guess-only" => args.print_guess_only = true,
+            "--bucketize" => args.bucketize = Some(value()?),
+            "--outdir" => args.outdir = Some(value()?),
+            "--precompute" => args.precompute = true,
+            "--practice" => args.practice = true,
+            "--stats-file" => args.stats_file = Some(value()?),
+            "--book" => args.book = Some(value()?),
+            "--share" => args.share = true,
+            "--lower-bound" => args.lower_bound = true,
+            "--alpha" => {
+                // NaN would score every guess NaN, so none would ever be chosen
+                let alpha: f64 = value()?
+                    .parse()
+                    .ok()
+                    .filter(|alpha| (0.0..=1.0).contains(alpha))
+                    .ok_or_else(|| "--alpha must be a number from 0 to 1".to_string())?;
+                args.alpha = Some(alpha);
+                args.strategy = Strategy::Blended(alpha);
+            }
+            "--list-limit" => {
+                args.list_limit = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "--list-limit must be an integer".to_string())?,
+                )
+            }
+            "--top" => {
+                args.top = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "--top must be an integer".to_string())?,
+                )
+            }
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
     }
 
-    bucket
+    Ok(args)
 }
 
-fn bucketize_answers(answers: &Vec<Word>, pattern: Word) -> [Vec<Word>; NUM_BUCKETS] {
-    const EMPTY_VEC: Vec<Word> = Vec::new();
-    let mut buckets = [EMPTY_VEC; NUM_BUCKETS];
-    for &answer in answers {
-        let bucket = get_bucket(pattern, answer);
-        buckets[bucket].push(answer);
+// A line entered at the result prompt: either a result or a command.
+enum Input {
+    Result(usize),
+    SetStrategy(Strategy),
+    Maybe,
+    Reload(String),
+    // Print the candidates; `true` for all of them regardless of --list-limit.
+    List(bool),
+    Suggest,
+}
+
+// Results: + = match-in-place; - = match-out-of-place; . = no match
+// Commands: `strat <minimax|entropy|rollout|balanced|blended>` switches the active strategy;
+// `maybe` lists near-miss words in case a result was mistyped;
+// `reload <path>` restarts the game with another dictionary;
+// `list` prints the remaining candidates (`list all` for more than --list-limit);
+// `suggest` shows the best few guesses without advancing the game.
+fn parse_input(line: &str) -> Result<Input, String> {
+    let line = line.trim();
+
+    match line {
+        "suggest" => return Ok(Input::Suggest),
+        "list" => return Ok(Input::List(false)),
+        "list all" => return Ok(Input::List(true)),
+        _ => {}
+    }
+
+    if let Some(path) = line.strip_prefix("reload ") {
+        return Ok(Input::Reload(path.trim().to_string()));
+    }
+
+    if line == "maybe" {
+        return Ok(Input::Maybe);
     }
-    buckets
+
+    if let Some(name) = line.strip_prefix("strat ") {
+        return Strategy::from_name(name.trim())
+            .map(Input::SetStrategy)
+            .ok_or_else(|| format!("Unknown strategy: {}", name.trim()));
+    }
+
+    parse_result(line).map(Input::Result)
 }
 
-fn bucket_counts(answers: &Vec<Word>, pattern: Word) -> [usize; NUM_BUCKETS] {
-    let mut counts = [0; NUM_BUCKETS];
-    for &answer in answers {
-        let bucket = get_bucket(pattern, answer);
-        counts[bucket] += 1;
+// None at the end of input.
+fn read_input() -> Option<Input> {
+    loop {
+        print!("Enter result (+/-/.): ");
+        io::stdout().flush().unwrap();
+        let line = io::stdin().lock().lines().next()?.unwrap();
+        match parse_input(&line) {
+            Ok(input) => return Some(input),
+            Err(message) => println!("{}", message),
+        }
     }
-    counts
 }
 
-fn get_best_pattern(answers: &Vec<Word>, guesses: &Vec<Word>) -> Word {
-    let mut best_pattern: Word = Default::default();
-    let mut best_score = answers.len() + 1;
+const DEFAULT_TOP: usize = 10;
 
-    for &pattern in guesses.iter().progress_with(
-        ProgressBar::new(guesses.len() as u64).with_style(
-            ProgressStyle::default_bar()
-                .template("Finding pattern: [{elapsed} / {duration}] {wide_bar} {pos}/{len}"),
-        ),
-    ) {
-        // The "score" of a pattern is the size of the largest bucket it splits
-        // answers into; lower is better.
-        let mut score = bucket_counts(answers, pattern).into_iter().max().unwrap();
-        
-        // Slightly prefer patterns that could also be an answer, in case we get lucky.
-        // This helps break ties when there are only a few answers left.
-        if answers.contains(&pattern) {
-            score -= 1;
-        }
-
-        if score < best_score {
-            best_pattern = pattern.clone();
-            best_score = score;
+// Below this many answers, compute the opener rather than using FIRST_GUESS.
+const DEFAULT_OPENER_THRESHOLD: usize = 500;
+
+// The opener for these pools. `--first auto` always computes an opener; `--first
+// auto-fast` only considers openers from the answer pool, which is much faster with
+// a large guess pool and usually finds one nearly as good.
+fn choose_opener(
+    args: &Args,
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    options: SearchOptions,
+) -> Word {
+    let threshold = args.opener_threshold.unwrap_or(DEFAULT_OPENER_THRESHOLD);
+    // A full search over a large pool takes a while, so show it and let Ctrl-C cut it short
+    let searching = SearchOptions {
+        show_progress: true,
+        ..options
+    };
+    let (first, interrupted) = interruptible(|| match args.first.as_deref() {
+        Some("auto") => compute_opener(answers, guesses, args.strategy, searching),
+        Some("auto-fast") => compute_opener(answers, answers, args.strategy, searching),
+        Some(s) => word_arg("--first", s),
+        None => default_opener(answers, guesses, args.strategy, options, threshold),
+    });
+    if interrupted {
+        eprintln!("Search interrupted; using best opener so far");
+    }
+    first
+}
+
+const DEFAULT_LIST_LIMIT: usize = 50;
+
+// How many guesses the `suggest` command shows.
+const SUGGEST_COUNT: usize = 5;
+
+// The pattern about to be played, then the best alternatives to it.
+// Ctrl-C stops scoring, leaving the best of the guesses scored so far.
+fn print_suggestions(state: &GameState, guesses: &[Word]) {
+    let (best, interrupted) = interruptible(|| {
+        best_patterns(
+            &state.answers,
+            guesses,
+            state.strategy,
+            state.options,
+            SUGGEST_COUNT,
+        )
+    });
+    if interrupted {
+        eprintln!("Search interrupted; showing the guesses scored so far");
+    }
+    let alternatives = best
+        .into_iter()
+        .filter(|&(guess, _)| guess != state.pattern)
+        .take(SUGGEST_COUNT - 1);
+    let current = (
+        state.pattern,
+        pattern_score(&state.answers, state.pattern, state.strategy, state.options),
+    );
+
+    println!("{:<8} {:>10} {:>8}", "guess", "score", "expect");
+    for (guess, score) in std::iter::once(current).chain(alternatives) {
+        let expected = expected_remaining(&bucket_counts(&state.answers, guess));
+        println!(
+            "{:<8} {:>10.3} {:>8.1}",
+            word_to_string(&guess),
+            score,
+            expected
+        );
+    }
+}
+
+// Print up to `limit` words, noting how many were left out.
+fn print_words(words: &[Word], limit: usize) {
+    for word in words.iter().take(limit) {
+        println!("{}", word_to_string(word));
+    }
+    if words.len() > limit {
+        println!("... and {} more", words.len() - limit);
+    }
+}
+
+// Parse a word given on the command line, exiting with an error if it can't be one.
+fn word_arg(flag: &str, s: &str) -> Word {
+    let word = string_to_word(s).unwrap_or_else(|message| {
+        eprintln!("{} {}: {}", flag, s, message);
+        std::process::exit(1);
+    });
+    if let Some(c) = unknown_char(&word) {
+        eprintln!(
+            "{} {}: {:?} doesn't appear in any dictionary word",
+            flag, s, c
+        );
+        std::process::exit(1);
+    }
+    word
+}
+
+// Read a history file of `guess result` lines, as accumulated by a script calling
+// the one-shot mode repeatedly.
+fn read_history(path: &str) -> Result<Vec<(Word, usize)>, String> {
+    let file = File::open(path).map_err(|error| format!("{}: {}", path, error))?;
+    let mut steps = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|error| format!("{}: {}", path, error))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (guess, result) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("Invalid history line: {}", line))?;
+        let result = parse_result(result.trim())
+            .map_err(|message| format!("Invalid history line: {}: {}", line, message))?;
+        steps.push((word_arg("--history", guess), result));
+    }
+    Ok(steps)
+}
+
+// One step of a scripted solve: apply the history and this turn's result, print the
+// remaining candidates and the next guess, and exit.
+// Returns how many candidates remain.
+fn one_shot(mut state: GameState, guesses: &Vec<Word>, steps: &[(Word, usize)]) -> usize {
+    for &(guess, result) in steps {
+        for conflict in state.apply_result(guess, result) {
+            eprintln!("Warning: {}", conflict);
+        }
+    }
+
+    println!("{} candidates", state.answers.len());
+    for answer in &state.answers {
+        println!("{}", word_to_string(answer));
+    }
+
+    match state.answers.len() {
+        0 => return 0,
+        1 => state.pattern = state.answers[0],
+        _ => state.suggest(guesses),
+    }
+    println!("suggest: {}", word_to_string(&state.pattern));
+    state.answers.len()
+}
+
+// Exit codes for --exit-code-count. 0 means the last result was all green, i.e.
+// solved, so `if wordle-bot ...` reads naturally. 1 is any error, always with a
+// message on stderr. 2 means no candidates are left: the results contradict each
+// other. Otherwise N candidates exit with N + 2, so one candidate is 3, and 254
+// stands for 252 or more.
+const EXIT_SOLVED: i32 = 0;
+const EXIT_COUNT_OFFSET: usize = 2;
+const EXIT_COUNT_CAP: usize = 254;
+
+fn count_exit_code(remaining: usize, solved: bool) -> i32 {
+    if solved {
+        EXIT_SOLVED
+    } else {
+        (remaining + EXIT_COUNT_OFFSET).min(EXIT_COUNT_CAP) as i32
+    }
+}
+
+// The minimal interface for autoplay bots: stdout gets nothing but each suggested
+// guess on its own line, and results are read from stdin without prompting.
+fn play_guess_only(mut state: GameState, guesses: &Vec<Word>) {
+    let mut lines = io::stdin().lock().lines();
+
+    loop {
+        println!("{}", word_to_string(&state.pattern));
+        io::stdout().flush().unwrap();
+
+        let result = loop {
+            let Some(line) = lines.next() else { return };
+            match parse_result(line.unwrap().trim()) {
+                Ok(result) => break result,
+                Err(message) => eprintln!("{}", message),
+            }
+        };
+        if result == NUM_BUCKETS - 1 {
+            return;
+        }
+
+        state.apply_result(state.pattern, result);
+        match state.answers.len() {
+            0 => {
+                eprintln!("No words found");
+                std::process::exit(1);
+            }
+            1 => state.pattern = state.answers[0],
+            _ => state.suggest(guesses),
+        }
+    }
+}
+
+// Append one game's outcome to the stats file: the secret, then the number of
+// guesses taken or `loss`.
+fn record_game(path: &str, secret: Word, outcome: Option<usize>) {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    let outcome = outcome.map_or("loss".to_string(), |guesses| guesses.to_string());
+    writeln!(file, "{} {}", word_to_string(&secret), outcome).unwrap();
+}
+
+// Step a SplitMix64 generator, for picking practice secrets.
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut x = *state;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+// Practice mode: we pick a secret, the user guesses and sees results. `give up`
+// reveals the secret along with how the solver would finish from where the user
+// left off, and counts as a loss. Each game is followed by a fresh one until EOF.
+fn practice(template: &GameState, guesses: &Vec<Word>, seed: u64, stats_file: Option<&str>) {
+    let mut random = seed;
+    let mut lines = io::stdin().lock().lines();
+
+    loop {
+        let secret = template.answers[next_random(&mut random) as usize % template.answers.len()];
+        let mut state = GameState::new(
+            template.answers.clone(),
+            template.pattern,
+            template.strategy,
+            template.options,
+        );
+        state.no_grey_reuse = template.no_grey_reuse;
+        state.green_bonus = template.green_bonus;
+        state.trace = template.trace;
+        let mut turns = 0;
+        println!("New game: {} possible words", state.answers.len());
+
+        let outcome = loop {
+            print!("Enter guess (or `give up`): ");
             io::stdout().flush().unwrap();
+            let Some(line) = lines.next() else { return };
+            let line = line.unwrap();
+            let line = line.trim();
+
+            if line == "give up" {
+                println!("The word was {}", word_to_string(&secret));
+                match state.answers.len() {
+                    1 => state.pattern = state.answers[0],
+                    _ if turns > 0 => state.suggest(guesses),
+                    _ => {}
+                }
+                if let Ok(path) = autosolve(
+                    secret,
+                    &state.answers,
+                    guesses,
+                    state.pattern,
+                    state.strategy,
+                    state.options,
+                ) {
+                    let path: Vec<String> = path.iter().map(word_to_string).collect();
+                    println!("Solver's path from here: {}", path.join(" "));
+                }
+                break None;
+            }
+
+            let guess = match string_to_word(line) {
+                Ok(guess) if guesses.contains(&guess) => guess,
+                _ => {
+                    println!("Not in the word list: {}", line);
+                    continue;
+                }
+            };
+
+            turns += 1;
+            let result = get_bucket(guess, secret);
+            println!("{}", bucket_to_pattern(result));
+            if result == NUM_BUCKETS - 1 {
+                println!("Solved in {} guesses", turns);
+                break Some(turns);
+            }
+            state.apply_result(guess, result);
+            println!("{} possible words", state.answers.len());
+        };
+
+        if let Some(path) = stats_file {
+            record_game(path, secret, outcome);
         }
     }
+}
+
+// The saved opening book at `path` if it matches this setup, otherwise a fresh one
+// saved there for next time.
+// None if building was interrupted, in which case nothing is saved.
+fn load_or_build_book(path: &str, state: &GameState, guesses: &Vec<Word>) -> Option<OpeningBook> {
+    if let Some(book) = OpeningBook::load(path, &book_key(state, guesses)) {
+        return Some(book);
+    }
 
-    best_pattern
+    eprintln!("Building opening book {}", path);
+    let Some(book) = OpeningBook::build(state, guesses) else {
+        eprintln!("Opening book build interrupted; not saving {}", path);
+        return None;
+    };
+    if let Err(error) = book.save(path) {
+        eprintln!("Couldn't save opening book {}: {}", path, error);
+    }
+    Some(book)
 }
 
-// + = match-in-place; - = match-out-of-place; . = no match
-fn read_result() -> usize {
-    print!("Enter result (+/-/.): ");
-    io::stdout().flush().unwrap();
-    let line = io::stdin().lock().lines().next().unwrap().unwrap();
-    let mut bucket = 0;
-    for c in line.chars() {
-        bucket *= 3;
-        match c {
-            '+' => bucket += 2, // Match-in-place: 2
-            '-' => bucket += 1, // Match-out-of-place: 1
-            '.' => bucket += 0, // No match: 0
-            _ => panic!("Invalid character"),
+// The opening book for people: every result the opener can get, most common first,
+// with the second guess to play.
+fn write_cheatsheet(out: &mut dyn Write, state: &GameState, book: &OpeningBook) -> io::Result<()> {
+    let counts = bucket_counts(&state.answers, state.pattern);
+    let mut results: Vec<usize> = (0..NUM_BUCKETS - 1)
+        .filter(|&result| counts[result] > 0)
+        .collect();
+    results.sort_by_key(|&result| std::cmp::Reverse(counts[result]));
+
+    writeln!(out, "Open with {}, then:", word_to_string(&state.pattern))?;
+    for result in results {
+        if let Some(reply) = book.reply(result) {
+            writeln!(
+                out,
+                "{}  {}  ({} left)",
+                bucket_to_pattern(result),
+                word_to_string(&reply),
+                counts[result]
+            )?;
         }
     }
-    bucket
+    Ok(())
+}
+
+// When input runs out mid-game: the candidates are equally likely, so in the endgame
+// rank them by how well each would split the rest if it turned out wrong. Before
+// that, scoring every candidate against every other takes too long just to exit
+// (seconds on the full dictionary), so they're listed as they are.
+fn print_final_guesses(state: &GameState, args: &Args) {
+    let limit = args.list_limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    if state.answers.len() > ENDGAME {
+        println!("Out of input with {} candidates left:", state.answers.len());
+        print_words(&state.answers, limit);
+        return;
+    }
+
+    println!(
+        "Out of input with {} candidates left; best guesses if you must choose now:",
+        state.answers.len()
+    );
+    let ranked: Vec<Word> = score_all(
+        &state.answers,
+        &state.answers,
+        state.strategy,
+        state.options,
+    )
+    .into_iter()
+    .map(|(answer, _)| answer)
+    .collect();
+    print_words(&ranked, limit);
+}
+
+// The clipboard's text, from whichever of the usual clipboard tools is installed.
+#[cfg(feature = "clipboard")]
+fn read_clipboard() -> Result<String, String> {
+    let tools: [&[&str]; 4] = [
+        &["pbpaste"],
+        &["wl-paste", "--no-newline"],
+        &["xclip", "-selection", "clipboard", "-out"],
+        &["xsel", "--clipboard", "--output"],
+    ];
+    for tool in tools {
+        if let Ok(output) = std::process::Command::new(tool[0])
+            .args(&tool[1..])
+            .output()
+        {
+            if output.status.success() {
+                return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+            }
+        }
+    }
+    Err("Couldn't read the clipboard: install pbpaste, wl-paste, xclip or xsel".to_string())
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn read_clipboard() -> Result<String, String> {
+    Err("Can't read the clipboard: rebuild with `--features clipboard`".to_string())
+}
+
+// Replay a finished game from its share text against the day's answer: the player's
+// grid next to the solver's, and the guesses the solver would have made.
+fn replay_share(
+    text: &str,
+    answer: Word,
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    first: Word,
+    strategy: Strategy,
+    options: SearchOptions,
+) -> Result<(), String> {
+    let results = parse_share_grid(text)?;
+    let path = autosolve(answer, answers, guesses, first, strategy, options)
+        .map_err(|message| format!("{}: {}", word_to_string(&answer), message))?;
+
+    println!("You {}/6, solver {}/6", results.len(), path.len());
+    for turn in 0..results.len().max(path.len()) {
+        let yours = results
+            .get(turn)
+            .map(|&result| bucket_to_emoji(result))
+            .unwrap_or_else(|| " ".repeat(WORD_LEN));
+        match path.get(turn) {
+            Some(guess) => println!(
+                "{}   {} {}",
+                yours,
+                bucket_to_emoji(get_bucket(*guess, answer)),
+                word_to_string(guess)
+            ),
+            None => println!("{}", yours),
+        }
+    }
+    Ok(())
+}
+
+fn load_pools(
+    args: &Args,
+    answers_path: &str,
+    guesses_path: &str,
+    comment_prefix: &str,
+) -> Result<(Vec<Word>, Vec<Word>), String> {
+    let (mut answers, guesses) = read_pools(
+        answers_path,
+        guesses_path,
+        args.pool_mode,
+        comment_prefix,
+        args.allow_punctuation,
+    )?;
+
+    // Themed variants whose answers never use some letters. They can still be guessed.
+    if let Some(letters) = &args.forbid_letters {
+        let before = answers.len();
+        answers.retain(|answer| !answer.iter().any(|&c| letters.contains(c)));
+        eprintln!(
+            "--forbid-letters {}: removed {} answers",
+            letters,
+            before - answers.len()
+        );
+    }
+    if answers.is_empty() || guesses.is_empty() {
+        return Err("dictionary is empty or contains no valid words".to_string());
+    }
+    Ok((answers, guesses))
 }
 
 fn main() {
-    let mut answers = read_words("dictionaries/wordle.txt");
-    let guesses = answers.clone();
-    let mut pattern = FIRST_GUESS;
+    let args = parse_args().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    });
+    // A panic's usual 101 is a candidate count under --exit-code-count, so exit 1
+    // like any other error
+    if args.exit_code_count {
+        let report = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            report(info);
+            std::process::exit(1);
+        }));
+    }
+    let answers_path = args.answers.as_deref().unwrap_or(DEFAULT_DICTIONARY);
+    let guesses_path = args.guesses.as_deref().unwrap_or(answers_path);
+    let comment_prefix = args
+        .comment_prefix
+        .clone()
+        .unwrap_or_else(|| DEFAULT_COMMENT_PREFIX.to_string());
+    let (answers, mut guesses) = load_pools(&args, answers_path, guesses_path, &comment_prefix)
+        .unwrap_or_else(|message| {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        });
+    let options = SearchOptions {
+        no_answer_bias: args.no_answer_bias,
+        ..Default::default()
+    };
+
+    install_interrupt_handler();
+    let first = choose_opener(&args, &answers, &guesses, options);
+
+    // Batch modes score every guess many times over, so the table pays for itself there
+    let batch = args.archive.is_some()
+        || args.batch.is_some()
+        || args.hardest.is_some()
+        || args.partitions.as_deref() == Some("all")
+        || args.benchmark
+        || args.benchmark_adversarial
+        || args.tree
+        || args.export_table.is_some();
+    let max_table_bytes = args
+        .max_table_mem
+        .map_or(DEFAULT_MAX_TABLE_BYTES, |mb| mb << 20);
+    if args.precompute || batch {
+        precompute_table(&guesses, &answers, max_table_bytes);
+    }
+
+    let mut out: Box<dyn Write> = match &args.out {
+        Some(path) => Box::new(File::create(path).unwrap_or_else(|error| {
+            eprintln!("{}: {}", path, error);
+            std::process::exit(1);
+        })),
+        None => Box::new(io::stdout()),
+    };
+
+    if let Some(archive_path) = &args.archive {
+        let exported = export_archive_curve(
+            archive_path,
+            &mut out,
+            &answers,
+            &guesses,
+            first,
+            args.strategy,
+            options,
+        );
+        if let Err(message) = exported {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.from_clipboard {
+        let answer = match &args.answer {
+            Some(s) => word_arg("--answer", s),
+            None => {
+                eprintln!("--from-clipboard needs the day's answer: pass --answer <word>");
+                std::process::exit(1);
+            }
+        };
+        let replayed = read_clipboard().and_then(|text| {
+            replay_share(
+                &text,
+                answer,
+                &answers,
+                &guesses,
+                first,
+                args.strategy,
+                options,
+            )
+        });
+        if let Err(message) = replayed {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(k) = args.hardest {
+        if let Err(message) = print_hardest(
+            &mut out,
+            &answers,
+            &guesses,
+            first,
+            args.strategy,
+            options,
+            k,
+        ) {
+            eprintln!("Can't build the decision tree: {}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(batch_path) = &args.batch {
+        let solved = solve_batch(
+            batch_path,
+            &mut out,
+            &answers,
+            &guesses,
+            first,
+            args.strategy,
+            options,
+        );
+        if let Err(message) = solved {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // `--partitions all` ranks every guess; otherwise report on the single word given
+    if let Some(word) = &args.partitions {
+        if word == "all" {
+            let ranked = rank_by_partitions(&answers, &guesses);
+            for (guess, count) in ranked.iter().take(args.top.unwrap_or(DEFAULT_TOP)) {
+                writeln!(out, "{} {}", word_to_string(guess), count).unwrap();
+            }
+        } else {
+            let pattern = word_arg("--partitions", word);
+            writeln!(out, "{}", partition_count(&answers, pattern)).unwrap();
+        }
+        return;
+    }
+
+    // How well one opener does on its own: answers it pins down to a single
+    // candidate, its worst bucket, and the expected candidates left
+    if let Some(word) = &args.opener_stats {
+        let opener = word_arg("--opener-stats", word);
+        let counts = bucket_counts(&answers, opener);
+        let singletons = counts
+            .iter()
+            .filter(|&(bucket, &count)| bucket != NUM_BUCKETS - 1 && count == 1)
+            .count();
+        writeln!(
+            out,
+            "{}: {} answers pinned down, largest bucket {}, average remaining {:.1}",
+            word_to_string(&opener),
+            singletons,
+            counts.values().max().unwrap(),
+            expected_remaining(&counts)
+        )
+        .unwrap();
+        return;
+    }
+
+    if let Some(word) = &args.bucketize {
+        let pattern = word_arg("--bucketize", word);
+        let outdir = args.outdir.as_deref().unwrap_or(".");
+        export_buckets(&answers, pattern, Path::new(outdir)).unwrap();
+        return;
+    }
+
+    // With --alpha, benchmark just that blend, so a shell loop can sweep alphas
+    if args.benchmark {
+        let strategies = match args.alpha {
+            Some(alpha) => vec![Strategy::Blended(alpha)],
+            None => Strategy::ALL.to_vec(),
+        };
+        for strategy in strategies {
+            let counts =
+                benchmark(&answers, &guesses, first, strategy, options).unwrap_or_else(|message| {
+                    eprintln!("Can't benchmark {:?}: {}", strategy, message);
+                    std::process::exit(1);
+                });
+            let total: usize = counts.iter().map(|&(_, count)| count).sum();
+            let average = total as f64 / counts.len() as f64;
+            writeln!(
+                out,
+                "{:?}: average {:.4} guesses, worst {}",
+                strategy,
+                average,
+                counts.iter().map(|&(_, count)| count).max().unwrap()
+            )
+            .unwrap();
+        }
+        return;
+    }
+
+    if args.lower_bound {
+        let bound = lower_bound(&answers, &guesses);
+        writeln!(
+            out,
+            "counting bound: average >= {:.4} guesses",
+            bound.counting
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "entropy estimate: average ~{:.4} guesses",
+            bound.entropy
+        )
+        .unwrap();
+        return;
+    }
+
+    if args.benchmark_adversarial {
+        for strategy in Strategy::ALL {
+            let worst = adversarial_guesses(&answers, &guesses, first, strategy, options)
+                .unwrap_or_else(|message| {
+                    eprintln!("Can't benchmark {:?}: {}", strategy, message);
+                    std::process::exit(1);
+                });
+            writeln!(out, "{:?}: worst {} guesses", strategy, worst).unwrap();
+        }
+        return;
+    }
+
+    if let Some(path) = &args.export_table {
+        let tree = build_tree(&answers, &guesses, first, args.strategy, options).unwrap_or_else(
+            |message| {
+                eprintln!("Can't build the decision tree: {}", message);
+                std::process::exit(1);
+            },
+        );
+        let mut file = io::BufWriter::new(File::create(path).unwrap());
+        write_tree_table(&tree, &mut file).unwrap();
+        return;
+    }
+
+    if args.tree {
+        let tree = build_tree(&answers, &guesses, first, args.strategy, options).unwrap_or_else(
+            |message| {
+                eprintln!("Can't build the decision tree: {}", message);
+                std::process::exit(1);
+            },
+        );
+        write_tree_json(&tree, &mut out, 0).unwrap();
+        writeln!(out).unwrap();
+        return;
+    }
+
+    let tie_seed = if args.shuffle_ties {
+        let seed = args.seed.unwrap_or_else(|| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            now.as_nanos() as u64
+        });
+        eprintln!("Shuffling ties with seed {}", seed);
+        Some(seed)
+    } else {
+        None
+    };
+
+    if answers.len() == 1 {
+        // Bots reading --print-guess-only output expect nothing but the word
+        if args.print_guess_only {
+            println!("{}", word_to_string(&answers[0]));
+        } else {
+            println!("Only possible answer: {}", word_to_string(&answers[0]));
+        }
+        return;
+    }
+
+    let mut state = GameState::new(
+        answers,
+        first,
+        args.strategy,
+        SearchOptions {
+            tie_seed,
+            ..options
+        },
+    );
+    state.no_grey_reuse = args.no_grey_reuse;
+    state.green_bonus = args.green_bonus;
+    state.trace = args.trace;
+
+    if let Some(path) = &args.cheatsheet {
+        let book = match &args.book {
+            Some(book_path) => load_or_build_book(book_path, &state, &guesses),
+            None => OpeningBook::build(&state, &guesses),
+        };
+        let Some(book) = book else {
+            eprintln!("Cheatsheet interrupted");
+            std::process::exit(1);
+        };
+        let mut file = File::create(path).unwrap();
+        write_cheatsheet(&mut file, &state, &book).unwrap();
+        return;
+    }
+
+    if args.practice {
+        let seed = args.seed.unwrap_or_else(|| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            now.as_nanos() as u64
+        });
+        practice(&state, &guesses, seed, args.stats_file.as_deref());
+        return;
+    }
+
+    if args.guess.is_some() || args.history.is_some() {
+        let mut steps = match &args.history {
+            Some(path) => read_history(path).unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            }),
+            None => Vec::new(),
+        };
+        match (&args.guess, &args.result) {
+            (Some(guess), Some(result)) => {
+                let result = parse_result(result).unwrap_or_else(|message| {
+                    eprintln!("--result {}: {}", result, message);
+                    std::process::exit(1);
+                });
+                steps.push((word_arg("--guess", guess), result));
+            }
+            (None, None) => {}
+            _ => {
+                eprintln!("--guess and --result must be given together");
+                std::process::exit(1);
+            }
+        }
+
+        state.options.show_progress = false;
+        let solved = steps
+            .last()
+            .is_some_and(|&(_, result)| result == NUM_BUCKETS - 1);
+        let remaining = one_shot(state, &guesses, &steps);
+        if args.stats {
+            eprintln!("[stats] {} bucket evaluations", bucket_evaluations());
+        }
+        if args.exit_code_count {
+            std::process::exit(count_exit_code(remaining, solved));
+        }
+        return;
+    }
+
+    if args.print_guess_only {
+        state.options.show_progress = false;
+        state.trace = false;
+        play_guess_only(state, &guesses);
+        return;
+    }
+
+    // The second guess comes from the opening book if there is one. It's dropped
+    // if the strategy or dictionary changes mid-session.
+    let mut book = args
+        .book
+        .as_deref()
+        .and_then(|path| load_or_build_book(path, &state, &guesses));
+
+    // --stats reports the work since the last prompt; the first turn's includes
+    // loading, the opener and any precomputed table.
+    let mut evaluations = 0;
+    // Set once --confirm is waiting on the result for the last candidate
+    let mut confirming = false;
 
     loop {
         // User enters the selected pattern and sees a result
-        println!("{} possible words", answers.len());
-        println!("Enter pattern: {}", word_to_string(&pattern));
-        let result = read_result();
+        println!("{} possible words", state.answers.len());
+        println!("Enter pattern: {}", word_to_string(&state.pattern));
+        println!(
+            "expect ~{:.1} candidates remaining",
+            expected_remaining(&bucket_counts(&state.answers, state.pattern))
+        );
+        if state.answers.len() <= ENDGAME {
+            let (now, next) = state.win_chances();
+            println!(
+                "{:.0}% to win with this guess, {:.0}% to be left with one candidate",
+                now * 100.0,
+                next * 100.0
+            );
+        }
+        if args.stats {
+            let total = bucket_evaluations();
+            eprintln!(
+                "[stats] {} bucket evaluations this turn, {} this game",
+                total - evaluations,
+                total
+            );
+            evaluations = total;
+        }
+        let Some(input) = read_input() else {
+            println!();
+            // With --confirm the last candidate was already reported as found
+            if state.answers.len() > 1 {
+                print_final_guesses(&state, &args);
+            }
+            break;
+        };
+        let result = match input {
+            Input::Result(result) => result,
+            Input::SetStrategy(strategy) => {
+                println!("Switching to {:?}", strategy);
+                state.strategy = strategy;
+                book = None;
+                state.suggest(&guesses);
+                continue;
+            }
+            Input::Maybe => {
+                print_near_misses(&state.constraints, &guesses);
+                continue;
+            }
+            Input::Suggest => {
+                print_suggestions(&state, &guesses);
+                continue;
+            }
+            Input::List(all) => {
+                let limit = args.list_limit.unwrap_or(DEFAULT_LIST_LIMIT);
+                print_words(&state.answers, if all { usize::MAX } else { limit });
+                continue;
+            }
+            Input::Reload(path) => {
+                match load_pools(&args, &path, &path, &comment_prefix) {
+                    Ok((answers, reloaded)) => {
+                        if args.precompute {
+                            precompute_table(&reloaded, &answers, max_table_bytes);
+                        }
+                        guesses = reloaded;
+                        // Unless --first named a word, the opener was picked for the
+                        // old pools and may not suit (or even be in) the new ones
+                        let first = choose_opener(&args, &answers, &guesses, options);
+                        let mut fresh =
+                            GameState::new(answers, first, state.strategy, state.options);
+                        fresh.no_grey_reuse = state.no_grey_reuse;
+                        fresh.green_bonus = state.green_bonus;
+                        fresh.trace = state.trace;
+                        state = fresh;
+                        book = None;
+                        println!("Reloaded {}", path);
+                    }
+                    // Keep playing the current game if the new dictionary is bad
+                    Err(message) => println!("Couldn't reload: {}", message),
+                }
+                continue;
+            }
+        };
+
+        if let Some(answer) = state.solved_by(result) {
+            println!("Solved: {}", word_to_string(&answer));
+            if args.share {
+                println!("\n{}", state.share_grid());
+            }
+            break;
+        }
 
-        // Filter down answers to those that match the result
-        let buckets = bucketize_answers(&answers, pattern);
-        answers = buckets[result].clone();
+        // Filter down answers to those that match the result, flagging results that
+        // contradict earlier ones: usually a typo in this one or a previous one
+        for conflict in state.apply_result(state.pattern, result) {
+            println!("Warning: {}", conflict);
+        }
+        if state.trace {
+            println!("[trace] {} candidates after filtering", state.answers.len());
+        }
 
         // If we've found an answer, we're done.
         // Otherwise, select a new pattern.
-        if answers.is_empty() {
-            println!("No words found");
+        if state.answers.is_empty() {
+            if confirming {
+                println!(
+                    "Warning: {} was the only word left, so an earlier result must be wrong",
+                    word_to_string(&state.constraints.results.last().unwrap().0)
+                );
+            }
+            println!("No words found; closest matches:");
+            print_near_misses(&state.constraints, &guesses);
             break;
-        } else if answers.len() == 1 {
-            println!("Found word: {}", word_to_string(&answers[0]));
+        } else if state.answers.len() == 1 {
+            println!("Found word: {}", word_to_string(&state.answers[0]));
+            // With --confirm, play it and wait for the all-green result
+            if args.confirm {
+                state.pattern = state.answers[0];
+                confirming = true;
+                continue;
+            }
+            if args.share {
+                println!("\n{}", state.share_grid());
+            }
             break;
         } else {
-            pattern = get_best_pattern(&answers, &guesses);
+            println!("{}", state.constraints.summary());
+            let reply = match &book {
+                Some(book) if state.constraints.results.len() == 1 => book.reply(result),
+                _ => None,
+            };
+            match reply {
+                Some(reply) => state.pattern = reply,
+                None => state.suggest(&guesses),
+            }
+            if let Some(probe) = state.probe_word(&guesses) {
+                println!("No candidate tells the rest apart; these require a probe word");
+                state.pattern = probe;
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        format!(
+            "{}/dictionaries/fixtures/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        )
+    }
+
+    #[test]
+    fn empty_dictionary_is_an_error() {
+        let path = fixture("empty.txt");
+        let loaded = load_pools(&Args::default(), &path, &path, DEFAULT_COMMENT_PREFIX);
+        assert_eq!(
+            loaded.unwrap_err(),
+            "dictionary is empty or contains no valid words"
+        );
+    }
+
+    #[test]
+    fn forbidding_every_answer_is_an_error() {
+        let path = fixture("single.txt");
+        let args = Args {
+            forbid_letters: Some("c".to_string()),
+            ..Args::default()
+        };
+        assert!(load_pools(&args, &path, &path, DEFAULT_COMMENT_PREFIX).is_err());
+    }
+}
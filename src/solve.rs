@@ -0,0 +1,581 @@
+use crate::bucket::{bucket_counts, bucket_to_pattern, bucketize_answers, get_bucket, NUM_BUCKETS};
+use crate::strategy::{Scorer, SearchOptions, Strategy};
+use crate::table::BucketCounter;
+use crate::word::{alphabet, unknown_char, word_to_string, Word, FIRST_GUESS, WORD_LEN};
+use std::io::{self, Write};
+
+// Why a search can't go on when a guess leaves every candidate in one bucket. With
+// --pools custom, the guess pool may have nothing that tells the answers apart.
+fn cant_split(guess: Word, answers: usize) -> String {
+    format!(
+        "guess {} can't split {} answers",
+        word_to_string(&guess),
+        answers
+    )
+}
+
+// Play a full game against a known answer, returning every guess made in order.
+// The last guess is the answer itself. Fails if the answer isn't in the answer
+// list, or the guesses can't narrow the candidates down to it.
+pub fn autosolve(
+    answer: Word,
+    answers: &[Word],
+    guesses: &Vec<Word>,
+    first: Word,
+    strategy: Strategy,
+    options: SearchOptions,
+) -> Result<Vec<Word>, String> {
+    if !answers.contains(&answer) {
+        return Err("not in the dictionary".to_string());
+    }
+
+    let mut answers = answers.to_vec();
+    let mut pattern = first;
+    let mut path = Vec::new();
+
+    loop {
+        path.push(pattern);
+        if pattern == answer {
+            return Ok(path);
+        }
+
+        let result = get_bucket(pattern, answer);
+        let remaining = bucketize_answers(&answers, pattern)[result].clone();
+        if answers.len() > 1 && remaining.len() == answers.len() {
+            return Err(cant_split(pattern, answers.len()));
+        }
+        answers = remaining;
+
+        pattern = match answers.len() {
+            1 => answers[0],
+            _ => strategy.choose_pattern(&answers, guesses, options),
+        };
+    }
+}
+
+// The best opener for these answers, searching only the guesses in `pool`.
+pub fn compute_opener(
+    answers: &Vec<Word>,
+    pool: &Vec<Word>,
+    strategy: Strategy,
+    options: SearchOptions,
+) -> Word {
+    let opener = strategy.choose_pattern(answers, pool, options);
+    eprintln!(
+        "Computed opener {} for {} answers from {} guesses",
+        word_to_string(&opener),
+        answers.len(),
+        pool.len()
+    );
+    opener
+}
+
+// The opener when none is given. FIRST_GUESS is tuned for the standard dictionary;
+// lists with fewer than `threshold` answers, or that FIRST_GUESS can't even be
+// spelled in, get one computed for them.
+pub fn default_opener(
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    strategy: Strategy,
+    options: SearchOptions,
+    threshold: usize,
+) -> Word {
+    if answers.len() < threshold || unknown_char(&FIRST_GUESS).is_some() {
+        compute_opener(answers, guesses, strategy, options)
+    } else {
+        FIRST_GUESS
+    }
+}
+
+// The solver's full decision tree: the guess to play, and the subtree for each
+// result it can produce. The all-green result (the guess was the answer) is implicit,
+// so a node with no children is a single answer.
+pub struct DecisionTree {
+    pub guess: Word,
+    // Whether the guess is itself one of the answers at this node.
+    pub solves: bool,
+    pub children: Vec<(usize, DecisionTree)>,
+}
+
+// Build the tree by following the solver's own choices down every nonempty bucket.
+pub fn build_tree(
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    guess: Word,
+    strategy: Strategy,
+    options: SearchOptions,
+) -> Result<DecisionTree, String> {
+    let buckets = bucketize_answers(answers, guess);
+    if answers.len() > 1 && buckets.values().any(|subset| subset.len() == answers.len()) {
+        return Err(cant_split(guess, answers.len()));
+    }
+
+    let mut children = Vec::new();
+    for (bucket, subset) in buckets.iter() {
+        if subset.is_empty() || bucket == NUM_BUCKETS - 1 {
+            continue;
+        }
+
+        let next = match subset.len() {
+            1 => subset[0],
+            _ => strategy.choose_pattern(subset, guesses, options),
+        };
+        children.push((
+            bucket,
+            build_tree(subset, guesses, next, strategy, options)?,
+        ));
+    }
+
+    Ok(DecisionTree {
+        guess,
+        solves: !buckets[NUM_BUCKETS - 1].is_empty(),
+        children,
+    })
+}
+
+// Record how many guesses the tree takes to solve each of its answers.
+fn tree_guess_counts(tree: &DecisionTree, depth: usize, counts: &mut Vec<(Word, usize)>) {
+    if tree.solves {
+        counts.push((tree.guess, depth));
+    }
+    for (_, child) in &tree.children {
+        tree_guess_counts(child, depth + 1, counts);
+    }
+}
+
+// The guesses the tree makes for each of its answers, ending with the answer.
+pub fn tree_paths(tree: &DecisionTree) -> Vec<Vec<Word>> {
+    let mut paths = Vec::new();
+    collect_paths(tree, &mut Vec::new(), &mut paths);
+    paths
+}
+
+fn collect_paths(tree: &DecisionTree, path: &mut Vec<Word>, paths: &mut Vec<Vec<Word>>) {
+    path.push(tree.guess);
+    if tree.solves {
+        paths.push(path.clone());
+    }
+    for (_, child) in &tree.children {
+        collect_paths(child, path, paths);
+    }
+    path.pop();
+}
+
+// Guesses needed to solve every answer with `strategy`, found by walking the decision
+// tree so each distinct game state is only searched once.
+pub fn benchmark(
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    first: Word,
+    strategy: Strategy,
+    options: SearchOptions,
+) -> Result<Vec<(Word, usize)>, String> {
+    let tree = build_tree(answers, guesses, first, strategy, options)?;
+    let mut counts = Vec::new();
+    tree_guess_counts(&tree, 1, &mut counts);
+    Ok(counts)
+}
+
+// Analytic floors on the average guesses any strategy could need, for context on
+// how far the benchmark averages are from optimal.
+pub struct LowerBound {
+    // A sound bound: at most one answer is solved on guess 1, at most one per bucket
+    // of the best-partitioning guess on guess 2, and each later guess can multiply
+    // that by the number of non-green results. Fill those depths greedily.
+    pub counting: f64,
+    // log2(answers) bits are needed, and no guess gives more than the best opener's
+    // entropy. Optimistic, since later guesses gain less than the opener.
+    pub entropy: f64,
+}
+
+pub fn lower_bound(answers: &Vec<Word>, guesses: &[Word]) -> LowerBound {
+    let counter = BucketCounter::new(answers);
+    let mut max_partitions = 0;
+    let mut max_entropy: f64 = 0.0;
+    for &guess in guesses {
+        let counts = counter.counts(guess);
+        max_partitions = max_partitions.max(counts.values().filter(|&&count| count > 0).count());
+        max_entropy = max_entropy.max(-Strategy::Entropy.score(&counts.as_slice(), false));
+    }
+
+    let mut remaining = answers.len();
+    let mut capacity = 1;
+    let mut depth = 1;
+    let mut total = 0;
+    while remaining > 0 {
+        let solved = capacity.min(remaining);
+        total += solved * depth;
+        remaining -= solved;
+        capacity = match depth {
+            1 => max_partitions,
+            _ => capacity.saturating_mul(NUM_BUCKETS - 1),
+        };
+        depth += 1;
+    }
+
+    LowerBound {
+        counting: total as f64 / answers.len() as f64,
+        entropy: (answers.len() as f64).log2() / max_entropy,
+    }
+}
+
+// Absurdle's choice of result: whichever bucket keeps the most answers alive,
+// preferring not to concede the win when buckets tie.
+pub fn adversary_bucket(answers: &Vec<Word>, pattern: Word) -> usize {
+    let counts = bucket_counts(answers, pattern);
+    counts
+        .iter()
+        .max_by_key(|&(bucket, &count)| (count, bucket != NUM_BUCKETS - 1))
+        .unwrap()
+        .0
+}
+
+// Guesses `strategy` needs against an adversary that picks every result, from the
+// opener until the answer is the only candidate left and is played.
+pub fn adversarial_guesses(
+    answers: &[Word],
+    guesses: &Vec<Word>,
+    first: Word,
+    strategy: Strategy,
+    options: SearchOptions,
+) -> Result<usize, String> {
+    let mut answers = answers.to_vec();
+    let mut pattern = first;
+    let mut count = 1;
+
+    loop {
+        let bucket = adversary_bucket(&answers, pattern);
+        if bucket == NUM_BUCKETS - 1 {
+            return Ok(count);
+        }
+
+        let remaining = bucketize_answers(&answers, pattern)[bucket].clone();
+        if answers.len() > 1 && remaining.len() == answers.len() {
+            return Err(cant_split(pattern, answers.len()));
+        }
+        answers = remaining;
+
+        pattern = match answers.len() {
+            1 => answers[0],
+            _ => strategy.choose_pattern(&answers, guesses, options),
+        };
+        count += 1;
+    }
+}
+
+pub fn write_tree_json(tree: &DecisionTree, out: &mut dyn Write, depth: usize) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    write!(
+        out,
+        "{{\n{}  \"guess\": \"{}\",\n",
+        indent,
+        word_to_string(&tree.guess)
+    )?;
+    write!(out, "{}  \"children\": {{", indent)?;
+
+    for (i, (bucket, child)) in tree.children.iter().enumerate() {
+        let separator = if i == 0 { "" } else { "," };
+        write!(
+            out,
+            "{}\n{}    \"{}\": ",
+            separator,
+            indent,
+            bucket_to_pattern(*bucket)
+        )?;
+        write_tree_json(child, out, depth + 2)?;
+    }
+
+    if !tree.children.is_empty() {
+        write!(out, "\n{}  ", indent)?;
+    }
+    write!(out, "}}\n{}}}", indent)
+}
+
+// The decision tree as a compact binary blob, so a minimal client can play the
+// solver's games with no dictionaries or scoring. Little-endian throughout:
+//
+//   magic  b"WSTB", version u8 (1), word length u8
+//   alphabet: count u8, then each char as a u32
+//   root node, where a node is:
+//     guess: one u8 alphabet index per letter
+//     flags u8: bit 0 set if the guess is itself one of the answers at this node
+//     child count u8, then per child its result bucket as a u8 and its node
+//
+// Every branch the solver reaches playing its own guesses is covered. A game that
+// strays from the tree (a different guess was played) has to be solved on the fly.
+const TABLE_MAGIC: &[u8; 4] = b"WSTB";
+const TABLE_VERSION: u8 = 1;
+
+pub fn write_tree_table(tree: &DecisionTree, out: &mut dyn Write) -> io::Result<()> {
+    if NUM_BUCKETS > u8::MAX as usize + 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}-letter buckets don't fit in the table format", WORD_LEN),
+        ));
+    }
+    let alphabet = alphabet();
+    out.write_all(TABLE_MAGIC)?;
+    out.write_all(&[TABLE_VERSION, WORD_LEN as u8, alphabet.len() as u8])?;
+    for &c in &alphabet {
+        out.write_all(&(c as u32).to_le_bytes())?;
+    }
+    write_table_node(tree, &alphabet, out)
+}
+
+fn write_table_node(tree: &DecisionTree, alphabet: &[char], out: &mut dyn Write) -> io::Result<()> {
+    for c in &tree.guess {
+        out.write_all(&[alphabet.binary_search(c).unwrap() as u8])?;
+    }
+    out.write_all(&[tree.solves as u8, tree.children.len() as u8])?;
+    for (bucket, child) in &tree.children {
+        out.write_all(&[*bucket as u8])?;
+        write_table_node(child, alphabet, out)?;
+    }
+    Ok(())
+}
+
+// A solver that only replays an exported decision tree.
+pub struct Solver {
+    tree: DecisionTree,
+}
+
+impl Solver {
+    pub fn from_table(bytes: &[u8]) -> Result<Solver, String> {
+        let mut reader = TableReader { bytes, position: 0 };
+        if reader.take(4)? != TABLE_MAGIC {
+            return Err("not a solver table".to_string());
+        }
+        let [version, word_len, alphabet_len] = reader.take(3)? else {
+            unreachable!()
+        };
+        if *version != TABLE_VERSION || *word_len as usize != WORD_LEN {
+            return Err(format!(
+                "unsupported table version {} for {}-letter words",
+                version, word_len
+            ));
+        }
+
+        let mut alphabet = Vec::new();
+        for _ in 0..*alphabet_len {
+            let code = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+            alphabet.push(char::from_u32(code).ok_or("invalid alphabet character")?);
+        }
+
+        let tree = reader.node(&alphabet)?;
+        if reader.position != bytes.len() {
+            return Err("trailing bytes after the tree".to_string());
+        }
+        Ok(Solver { tree })
+    }
+
+    pub fn first_guess(&self) -> Word {
+        self.tree.guess
+    }
+
+    // The guess to play after this sequence of results to the solver's own guesses,
+    // or None if the results leave the tree (or didn't come from this tree's answers).
+    pub fn next_guess(&self, results: &[usize]) -> Option<Word> {
+        let mut node = &self.tree;
+        for &result in results {
+            node = &node
+                .children
+                .iter()
+                .find(|(bucket, _)| *bucket == result)?
+                .1;
+        }
+        Some(node.guess)
+    }
+}
+
+struct TableReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> TableReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.position + count;
+        let taken = self
+            .bytes
+            .get(self.position..end)
+            .ok_or("table ends unexpectedly")?;
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn node(&mut self, alphabet: &[char]) -> Result<DecisionTree, String> {
+        let mut guess: Word = Default::default();
+        for (c, &index) in guess.iter_mut().zip(self.take(WORD_LEN)?) {
+            *c = *alphabet
+                .get(index as usize)
+                .ok_or("letter outside the alphabet")?;
+        }
+        let [flags, child_count] = self.take(2)? else {
+            unreachable!()
+        };
+
+        let mut children = Vec::new();
+        for _ in 0..*child_count {
+            let bucket = self.take(1)?[0] as usize;
+            if bucket >= NUM_BUCKETS {
+                return Err(format!("invalid result bucket {}", bucket));
+            }
+            children.push((bucket, self.node(alphabet)?));
+        }
+        Ok(DecisionTree {
+            guess,
+            solves: flags & 1 != 0,
+            children,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::{precompute_table, DEFAULT_MAX_TABLE_BYTES};
+    use crate::testing::{dictionary_path, fixture, word, words};
+    use crate::word::{read_words, DEFAULT_COMMENT_PREFIX};
+
+    #[test]
+    fn small_dictionaries_compute_their_opener() {
+        let answers = words(&["fghij", "klmno", "pqrst"]);
+        let opener = default_opener(
+            &answers,
+            &answers,
+            Strategy::default(),
+            SearchOptions::default(),
+            500,
+        );
+        assert_ne!(opener, FIRST_GUESS);
+        assert_eq!(opener, word("fghij"));
+    }
+
+    #[test]
+    fn large_dictionaries_keep_the_standard_opener() {
+        let answers = words(&["fghij", "klmno", "pqrst"]);
+        let opener = default_opener(
+            &answers,
+            &answers,
+            Strategy::default(),
+            SearchOptions::default(),
+            3,
+        );
+        assert_eq!(opener, FIRST_GUESS);
+    }
+
+    #[test]
+    fn guesses_that_cant_split_are_an_error() {
+        // fzzzz only picks out fghij; nothing tells klmno and pqrst apart
+        let answers = words(&["fghij", "klmno", "pqrst"]);
+        let guesses = words(&["fzzzz"]);
+        let first = word("fzzzz");
+        let strategy = Strategy::default();
+        let options = SearchOptions::default();
+
+        assert_eq!(
+            autosolve(word("fghij"), &answers, &guesses, first, strategy, options),
+            Ok(words(&["fzzzz", "fghij"]))
+        );
+        let message = "guess fzzzz can't split 2 answers".to_string();
+        assert_eq!(
+            autosolve(word("klmno"), &answers, &guesses, first, strategy, options),
+            Err(message.clone())
+        );
+        assert_eq!(
+            build_tree(&answers, &guesses, first, strategy, options).err(),
+            Some(message.clone())
+        );
+        assert_eq!(
+            adversarial_guesses(&answers, &guesses, first, strategy, options),
+            Err(message)
+        );
+    }
+
+    #[test]
+    fn tree_paths_match_autosolve() {
+        let answers = fixture("cluster.txt");
+        let strategy = Strategy::default();
+        let options = SearchOptions::default();
+        let first = default_opener(&answers, &answers, strategy, options, 500);
+        let tree = build_tree(&answers, &answers, first, strategy, options).unwrap();
+
+        let paths = tree_paths(&tree);
+        assert_eq!(paths.len(), answers.len());
+        for path in paths {
+            let answer = *path.last().unwrap();
+            assert_eq!(
+                autosolve(answer, &answers, &answers, first, strategy, options),
+                Ok(path)
+            );
+        }
+    }
+
+    #[test]
+    fn exported_tables_replay_the_tree() {
+        let answers = fixture("split.txt");
+        let strategy = Strategy::default();
+        let options = SearchOptions::default();
+        let first = default_opener(&answers, &answers, strategy, options, 500);
+        let tree = build_tree(&answers, &answers, first, strategy, options).unwrap();
+        let mut bytes = Vec::new();
+        write_tree_table(&tree, &mut bytes).unwrap();
+
+        let solver = Solver::from_table(&bytes).unwrap();
+        assert_eq!(solver.first_guess(), first);
+        for &answer in &answers {
+            let path = autosolve(answer, &answers, &answers, first, strategy, options).unwrap();
+            let results: Vec<usize> = path
+                .iter()
+                .map(|&guess| get_bucket(guess, answer))
+                .collect();
+            for (turn, &guess) in path.iter().enumerate() {
+                assert_eq!(solver.next_guess(&results[..turn]), Some(guess));
+            }
+        }
+
+        assert_eq!(
+            Solver::from_table(&bytes[..bytes.len() - 1]).err(),
+            Some("table ends unexpectedly".to_string())
+        );
+        bytes[0] = b'X';
+        assert_eq!(
+            Solver::from_table(&bytes).err(),
+            Some("not a solver table".to_string())
+        );
+    }
+
+    // A ratchet on the bundled dictionary with the default opener and strategy.
+    // Wordle's guarantee is six guesses, but with every word in wordle.txt as a
+    // possible answer no strategy here gets there yet, so this pins the current
+    // worst case: lower WORST_GUESSES as scoring improves, never raise it. Run with
+    // `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore = "solves every word in the bundled dictionary"]
+    fn bundled_dictionary_worst_case_does_not_regress() {
+        const WORST_GUESSES: usize = 8;
+        let words = read_words(
+            &dictionary_path("wordle.txt"),
+            DEFAULT_COMMENT_PREFIX,
+            false,
+        )
+        .unwrap();
+        precompute_table(&words, &words, DEFAULT_MAX_TABLE_BYTES);
+        let strategy = Strategy::default();
+        let options = SearchOptions::default();
+        let first = default_opener(&words, &words, strategy, options, 500);
+
+        let over: Vec<String> = benchmark(&words, &words, first, strategy, options)
+            .unwrap()
+            .into_iter()
+            .filter(|&(_, count)| count > WORST_GUESSES)
+            .map(|(answer, count)| format!("{} ({})", word_to_string(&answer), count))
+            .collect();
+        assert!(
+            over.is_empty(),
+            "over {} guesses: {}",
+            WORST_GUESSES,
+            over.join(", ")
+        );
+    }
+}
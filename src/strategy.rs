@@ -0,0 +1,488 @@
+use crate::bucket::{bucket_counts, bucketize_answers, Buckets, NUM_BUCKETS};
+use crate::table::BucketCounter;
+use crate::word::Word;
+use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Every guess with its partition count, most partitions first.
+pub fn rank_by_partitions(answers: &Vec<Word>, guesses: &[Word]) -> Vec<(Word, usize)> {
+    let counter = BucketCounter::new(answers);
+    let mut ranked: Vec<(Word, usize)> = guesses
+        .iter()
+        .map(|&guess| {
+            let partitions = counter
+                .counts(guess)
+                .values()
+                .filter(|&&count| count > 0)
+                .count();
+            (guess, partitions)
+        })
+        .collect();
+    ranked.sort_by_key(|&(guess, count)| (std::cmp::Reverse(count), guess));
+    ranked
+}
+
+// A pattern is one of the answers exactly when some answer lands in the all-green
+// bucket. Reports false for every pattern when the answer bias is off.
+fn is_answer(counts: &Buckets<usize>, options: SearchOptions) -> bool {
+    !options.no_answer_bias && counts[NUM_BUCKETS - 1] > 0
+}
+
+// A single pattern's score under `strategy`, as get_best_pattern would score it.
+pub fn pattern_score(
+    answers: &Vec<Word>,
+    pattern: Word,
+    strategy: Strategy,
+    options: SearchOptions,
+) -> f64 {
+    let counts = bucket_counts(answers, pattern);
+    strategy.score(&counts.as_slice(), is_answer(&counts, options))
+}
+
+// Scores a guess from the bucket counts it splits the answers into, and whether the
+// guess could itself be the answer. Lower scores are better. Implemented by the
+// built-in strategies and by any `Fn(&[usize], bool) -> f64`, so library users can
+// plug their own scoring into get_best_pattern.
+pub trait Scorer {
+    fn score(&self, counts: &[usize], is_answer: bool) -> f64;
+}
+
+impl<F: Fn(&[usize], bool) -> f64> Scorer for F {
+    fn score(&self, counts: &[usize], is_answer: bool) -> f64 {
+        self(counts, is_answer)
+    }
+}
+
+// How to score a guess given the bucket counts it splits the answers into.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Strategy {
+    // Minimize the size of the largest bucket.
+    #[default]
+    Minimax,
+    // Maximize the expected information (in bits) gained from the result.
+    Entropy,
+    // Shortlist by entropy, then pick the shortlisted guess with the fewest expected
+    // guesses-to-solve under a one-level greedy rollout. See rollout_best_pattern.
+    Rollout,
+    // Minimize the variance of the nonempty buckets' sizes, for the most even
+    // partition however many buckets it has.
+    Balanced,
+    // alpha * largest bucket + (1 - alpha) * expected remaining answers: 1.0 is
+    // minimax, 0.0 optimizes the average.
+    Blended(f64),
+}
+
+pub const DEFAULT_ALPHA: f64 = 0.5;
+
+impl Strategy {
+    pub const ALL: [Strategy; 4] = [
+        Strategy::Minimax,
+        Strategy::Entropy,
+        Strategy::Rollout,
+        Strategy::Balanced,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Strategy> {
+        match name {
+            "minimax" => Some(Strategy::Minimax),
+            "entropy" => Some(Strategy::Entropy),
+            "rollout" => Some(Strategy::Rollout),
+            "balanced" => Some(Strategy::Balanced),
+            "blended" => Some(Strategy::Blended(DEFAULT_ALPHA)),
+            _ => None,
+        }
+    }
+
+    // The pattern this strategy plays next. Rollout searches rather than scores, so
+    // it's dispatched here; the rest go through get_best_pattern.
+    pub fn choose_pattern(
+        &self,
+        answers: &Vec<Word>,
+        guesses: &Vec<Word>,
+        options: SearchOptions,
+    ) -> Word {
+        match self {
+            Strategy::Rollout => rollout_best_pattern(answers, guesses, options),
+            _ => get_best_pattern(answers, guesses, *self, options),
+        }
+    }
+}
+
+impl Scorer for Strategy {
+    fn score(&self, counts: &[usize], is_answer: bool) -> f64 {
+        let total: usize = counts.iter().sum();
+        match self {
+            Strategy::Minimax => {
+                let mut score = *counts.iter().max().unwrap() as f64;
+
+                // Slightly prefer patterns that could also be an answer, in case we get lucky.
+                // This helps break ties when there are only a few answers left.
+                if is_answer {
+                    score -= 1.0;
+                }
+                score
+            }
+            // Rollout can't be scored from counts alone; rank by its entropy shortlist.
+            Strategy::Entropy | Strategy::Rollout => {
+                // Sum in sorted order: guesses with the same partition sizes in
+                // different buckets must score exactly equal to tie as documented.
+                let mut sizes: Vec<usize> =
+                    counts.iter().copied().filter(|&count| count > 0).collect();
+                sizes.sort_unstable();
+                let entropy: f64 = sizes
+                    .iter()
+                    .map(|&count| {
+                        let p = count as f64 / total as f64;
+                        -p * p.log2()
+                    })
+                    .sum();
+
+                // Same nudge as minimax, weighted by the chance the pattern is the answer.
+                let bonus = if is_answer { 1.0 / total as f64 } else { 0.0 };
+                -(entropy + bonus)
+            }
+            Strategy::Balanced => {
+                // Over every bucket, empty ones included, the variance only depends on
+                // sum(c^2) and ranks like expected remaining, so take it over the
+                // nonempty ones. A pattern that leaves everything together is even
+                // but useless, so it scores worst.
+                let buckets = counts.iter().filter(|&&count| count > 0).count();
+                if buckets == 1 && total > 1 {
+                    return f64::MAX;
+                }
+
+                // k^2 * variance = k * sum(c^2) - total^2 is kept an exact integer, so
+                // equal partitions score exactly equal whichever buckets they land in
+                let squares: usize = counts.iter().map(|&count| count * count).sum();
+                let variance =
+                    (buckets * squares - total * total) as f64 / (buckets * buckets) as f64;
+
+                // Variances of different partitions differ by at least
+                // 1 / NUM_BUCKETS^4, so this only breaks ties in favor of patterns
+                // that could be the answer.
+                let bonus = if is_answer {
+                    0.5 / (NUM_BUCKETS as f64).powi(4)
+                } else {
+                    0.0
+                };
+                variance - bonus
+            }
+            Strategy::Blended(alpha) => {
+                // Both terms get minimax's nudge: an answer's own bucket empties if
+                // we're lucky, so it doesn't count towards what remains.
+                let nudge = if is_answer { 1 } else { 0 };
+                let worst = (*counts.iter().max().unwrap() - nudge) as f64;
+                let squares: usize = counts.iter().map(|&count| count * count).sum();
+                let expected = (squares - nudge) as f64 / total as f64;
+                alpha * worst + (1.0 - alpha) * expected
+            }
+        }
+    }
+}
+
+// Set while an interactive search is running, so Ctrl-C cancels the search
+// instead of killing the process.
+pub(crate) static SEARCHING: AtomicBool = AtomicBool::new(false);
+pub(crate) static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_interrupt(_signal: libc::c_int) {
+    if SEARCHING.load(Ordering::SeqCst) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    } else {
+        unsafe { libc::_exit(130) };
+    }
+}
+
+// Run `search` so that Ctrl-C cuts it short with its best result so far instead of
+// exiting. Also returns whether that happened.
+pub fn interruptible<T>(search: impl FnOnce() -> T) -> (T, bool) {
+    SEARCHING.store(true, Ordering::SeqCst);
+    let result = search();
+    SEARCHING.store(false, Ordering::SeqCst);
+    (result, INTERRUPTED.swap(false, Ordering::SeqCst))
+}
+
+pub fn install_interrupt_handler() {
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            handle_interrupt as *const () as libc::sighandler_t,
+        );
+    }
+}
+
+// How many entropy-ranked guesses the rollout strategy considers.
+const ROLLOUT_SHORTLIST: usize = 5;
+
+// Rough guesses still needed to solve `k` candidates once we stop looking ahead:
+// one to play the answer if it's the only one left, otherwise assume each further
+// guess splits the field about three ways.
+fn rollout_leaf_estimate(k: usize) -> f64 {
+    match k {
+        0 => 0.0,
+        1 => 1.0,
+        _ => 1.0 + (k as f64).log(3.0),
+    }
+}
+
+// Expected guesses to solve `answers` starting with `pattern`, assuming we then play
+// the greedy (entropy-best) guess in each bucket and estimate what's left after that.
+// None if Ctrl-C cut the rollout short.
+fn rollout_cost(
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    pattern: Word,
+    options: SearchOptions,
+) -> Option<f64> {
+    let total = answers.len() as f64;
+    let mut cost = 1.0;
+
+    for (bucket, subset) in bucketize_answers(answers, pattern).iter() {
+        if subset.is_empty() || bucket == NUM_BUCKETS - 1 {
+            continue;
+        }
+
+        let subset_cost = if subset.len() == 1 {
+            1.0
+        } else {
+            let greedy = get_best_pattern(subset, guesses, Strategy::Entropy, options);
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                return None;
+            }
+            let counts = bucket_counts(subset, greedy);
+            1.0 + counts
+                .iter()
+                .filter(|&(bucket, _)| bucket != NUM_BUCKETS - 1)
+                .map(|(_, count)| count)
+                .map(|&count| count as f64 / subset.len() as f64 * rollout_leaf_estimate(count))
+                .sum::<f64>()
+        };
+        cost += subset.len() as f64 / total * subset_cost;
+    }
+
+    Some(cost)
+}
+
+// On Ctrl-C, stops with the best pattern rolled out so far, or entropy's pick if
+// none finished.
+fn rollout_best_pattern(answers: &Vec<Word>, guesses: &Vec<Word>, options: SearchOptions) -> Word {
+    // The inner searches are many and quick, so they never show progress
+    let options = SearchOptions {
+        show_progress: false,
+        ..options
+    };
+    let shortlist = best_patterns(
+        answers,
+        guesses,
+        Strategy::Entropy,
+        options,
+        ROLLOUT_SHORTLIST,
+    );
+    let (mut best_pattern, mut best_cost) = (shortlist[0].0, f64::INFINITY);
+    for &(pattern, _) in &shortlist {
+        let Some(cost) = rollout_cost(answers, guesses, pattern, options) else {
+            break;
+        };
+        if cost < best_cost {
+            best_pattern = pattern;
+            best_cost = cost;
+        }
+    }
+    best_pattern
+}
+
+// How get_best_pattern runs, independent of how it scores.
+#[derive(Clone, Copy, Default)]
+pub struct SearchOptions {
+    pub show_progress: bool,
+    // Break ties between equally-scored patterns pseudo-randomly with this seed,
+    // instead of taking the first one in dictionary order.
+    pub tie_seed: Option<u64>,
+    // Score by the raw metric alone, without nudging patterns that could be the
+    // answer ahead of equally good ones that can't. Purer for analysis, but may
+    // cost a little in average guesses. Also changes the `is_answer` scorers see.
+    pub no_answer_bias: bool,
+}
+
+// A reproducible pseudo-random rank for a pattern (SplitMix64 finalizer).
+fn tie_rank(seed: u64, pattern: Word) -> u64 {
+    let mut x = pattern
+        .iter()
+        .fold(seed, |acc, &c| acc.wrapping_mul(31).wrapping_add(c as u64));
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+pub fn get_best_pattern<S: Scorer>(
+    answers: &Vec<Word>,
+    guesses: &Vec<Word>,
+    scorer: S,
+    options: SearchOptions,
+) -> Word {
+    let mut best_pattern: Word = Default::default();
+    let mut best_score = f64::INFINITY;
+
+    let progress = if options.show_progress {
+        ProgressBar::new(guesses.len() as u64).with_style(
+            ProgressStyle::default_bar()
+                .template("Finding pattern: [{elapsed} / {duration}] {wide_bar} {pos}/{len}"),
+        )
+    } else {
+        ProgressBar::hidden()
+    };
+
+    let counter = BucketCounter::new(answers);
+    for &pattern in guesses.iter().progress_with(progress) {
+        let counts = counter.counts(pattern);
+        let score = scorer.score(&counts.as_slice(), is_answer(&counts, options));
+
+        let wins_tie = match options.tie_seed {
+            Some(seed) => {
+                score == best_score && tie_rank(seed, pattern) < tie_rank(seed, best_pattern)
+            }
+            None => false,
+        };
+
+        if score < best_score || wins_tie {
+            best_pattern = pattern;
+            best_score = score;
+            io::stdout().flush().unwrap();
+        }
+
+        // Bail out with the best pattern so far if the user hit Ctrl-C. The flag
+        // stays set so searches this one is part of (like rollout) stop too.
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+
+    best_pattern
+}
+
+// Every guess with its score under `strategy`, best first. Ties stay in dictionary order.
+// Ctrl-C during a search stops scoring, leaving just the guesses scored so far.
+pub fn score_all(
+    answers: &Vec<Word>,
+    guesses: &[Word],
+    strategy: Strategy,
+    options: SearchOptions,
+) -> Vec<(Word, f64)> {
+    let counter = BucketCounter::new(answers);
+    let mut scored: Vec<(Word, f64)> = Vec::new();
+    for &guess in guesses {
+        let counts = counter.counts(guess);
+        scored.push((
+            guess,
+            strategy.score(&counts.as_slice(), is_answer(&counts, options)),
+        ));
+
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+    scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    scored
+}
+
+// The `n` best guesses with their scores.
+pub fn best_patterns(
+    answers: &Vec<Word>,
+    guesses: &[Word],
+    strategy: Strategy,
+    options: SearchOptions,
+    n: usize,
+) -> Vec<(Word, f64)> {
+    let mut scored = score_all(answers, guesses, strategy, options);
+    scored.truncate(n);
+    scored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bucket::expected_remaining;
+    use crate::testing::{fixture, word};
+
+    #[test]
+    fn closures_plug_in_as_scorers() {
+        let answers = fixture("split.txt");
+        let most_partitions =
+            |counts: &[usize], _: bool| -(counts.iter().filter(|&&count| count > 0).count() as f64);
+        let chosen = get_best_pattern(
+            &answers,
+            &answers,
+            most_partitions,
+            SearchOptions::default(),
+        );
+        assert_eq!(chosen, rank_by_partitions(&answers, &answers)[0].0);
+    }
+
+    #[test]
+    fn no_answer_bias_hides_answers_from_scorers() {
+        let answers = fixture("split.txt");
+        let saw_answer = std::cell::Cell::new(false);
+        let scorer = |_: &[usize], is_answer: bool| {
+            saw_answer.set(saw_answer.get() || is_answer);
+            0.0
+        };
+
+        get_best_pattern(&answers, &answers, scorer, SearchOptions::default());
+        assert!(saw_answer.get());
+
+        saw_answer.set(false);
+        let options = SearchOptions {
+            no_answer_bias: true,
+            ..Default::default()
+        };
+        get_best_pattern(&answers, &answers, scorer, options);
+        assert!(!saw_answer.get());
+    }
+
+    #[test]
+    fn blended_extremes_match_minimax_and_expected_remaining() {
+        let answers = fixture("split.txt");
+        for &guess in &answers {
+            let counts = bucket_counts(&answers, guess);
+            for is_answer in [false, true] {
+                assert_eq!(
+                    Strategy::Blended(1.0).score(&counts.as_slice(), is_answer),
+                    Strategy::Minimax.score(&counts.as_slice(), is_answer)
+                );
+            }
+            assert_eq!(
+                Strategy::Blended(0.0).score(&counts.as_slice(), false),
+                expected_remaining(&counts)
+            );
+        }
+    }
+
+    #[test]
+    fn balanced_ranks_evenness_not_expected_remaining() {
+        // Expected remaining prefers the finer split; balanced the even one
+        let (even, fine) = ([3, 3], [1, 1, 1, 3]);
+        let average = Strategy::Blended(0.0);
+        assert!(average.score(&fine, false) < average.score(&even, false));
+        assert!(Strategy::Balanced.score(&even, false) < Strategy::Balanced.score(&fine, false));
+        // Leaving everything together is never balanced
+        assert!(Strategy::Balanced.score(&[6], false) > Strategy::Balanced.score(&fine, false));
+    }
+
+    #[test]
+    fn equal_partitions_prefer_answers_then_dictionary_order() {
+        let answers = fixture("ties.txt");
+        // As --pools subset builds them: the guess list, then the answers
+        let mut guesses = fixture("ties_guesses.txt");
+        guesses.extend(&answers);
+
+        let strategies = Strategy::ALL
+            .iter()
+            .copied()
+            .chain([Strategy::Blended(DEFAULT_ALPHA)]);
+        for strategy in strategies {
+            let chosen = strategy.choose_pattern(&answers, &guesses, SearchOptions::default());
+            assert_eq!(chosen, word("fghij"), "{:?}", strategy);
+        }
+    }
+}
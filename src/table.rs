@@ -0,0 +1,175 @@
+use crate::bucket::{bucket_counts, count_evaluations, get_bucket_in, Buckets, NUM_BUCKETS};
+use crate::word::{alphabet, index_space, word_to_index, Word, ALPHABET};
+use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
+use std::sync::{RwLock, RwLockReadGuard};
+
+// Every guess's bucket against every answer, computed once so scoring is a lookup
+// instead of a get_bucket call. Words find their row and column through dense
+// arrays indexed by word_to_index.
+pub struct BucketTable {
+    // The alphabet the indices were computed in. Loading another dictionary can
+    // grow the alphabet and shift every index, leaving the table stale.
+    alphabet: Vec<char>,
+    rows: Vec<u32>,
+    columns: Vec<u32>,
+    num_answers: usize,
+    buckets: Vec<u8>,
+}
+
+const NOT_IN_TABLE: u32 = u32::MAX;
+
+// Don't allocate dense lookups for alphabets with more words than this.
+const MAX_INDEX_SPACE: u64 = 1 << 26;
+
+// Words the alphabet can't spell are left out, and scored on the fly.
+fn dense_positions(alphabet: &[char], words: &[Word], space: usize) -> Vec<u32> {
+    let mut positions = vec![NOT_IN_TABLE; space];
+    for (i, word) in words.iter().enumerate() {
+        if let Some(index) = word_to_index(alphabet, word) {
+            positions[index as usize] = i as u32;
+        }
+    }
+    positions
+}
+
+impl BucketTable {
+    // None if the alphabet is too large to index densely, or words are too long for
+    // a bucket to fit in a byte.
+    pub fn new(guesses: &[Word], answers: &[Word]) -> Option<BucketTable> {
+        let alphabet = ALPHABET.read().unwrap();
+        let space = index_space(&alphabet);
+        if space > MAX_INDEX_SPACE || NUM_BUCKETS > u8::MAX as usize + 1 {
+            return None;
+        }
+
+        let mut buckets = vec![0; guesses.len() * answers.len()];
+        count_evaluations(buckets.len());
+        for (guess, row) in guesses
+            .iter()
+            .zip(buckets.chunks_mut(answers.len()))
+            .progress_with(
+                ProgressBar::new(guesses.len() as u64).with_style(
+                    ProgressStyle::default_bar()
+                        .template("Precomputing: [{elapsed} / {duration}] {wide_bar} {pos}/{len}"),
+                ),
+            )
+        {
+            for (&answer, bucket) in answers.iter().zip(row.iter_mut()) {
+                *bucket = get_bucket_in(&alphabet, *guess, answer) as u8;
+            }
+        }
+        let snapshot = alphabet.clone();
+        drop(alphabet);
+
+        Some(BucketTable {
+            rows: dense_positions(&snapshot, guesses, space as usize),
+            columns: dense_positions(&snapshot, answers, space as usize),
+            alphabet: snapshot,
+            num_answers: answers.len(),
+            buckets,
+        })
+    }
+
+    fn is_current(&self) -> bool {
+        *ALPHABET.read().unwrap() == self.alphabet
+    }
+
+    fn position(&self, positions: &[u32], word: &Word) -> Option<usize> {
+        let index = word_to_index(&self.alphabet, word)?;
+        match positions.get(index as usize) {
+            Some(&position) if position != NOT_IN_TABLE => Some(position as usize),
+            _ => None,
+        }
+    }
+
+    pub fn bucket(&self, guess: Word, answer: Word) -> Option<usize> {
+        if !self.is_current() {
+            return None;
+        }
+        let row = self.position(&self.rows, &guess)?;
+        let column = self.position(&self.columns, &answer)?;
+        Some(self.buckets[row * self.num_answers + column] as usize)
+    }
+}
+
+static TABLE: RwLock<Option<BucketTable>> = RwLock::new(None);
+
+pub const DEFAULT_MAX_TABLE_BYTES: u64 = 1 << 30;
+
+// Roughly how much memory a table for these pools takes: a byte per guess and
+// answer pair, plus the two dense lookups.
+pub fn table_bytes(guesses: usize, answers: usize) -> u64 {
+    let lookups = 2 * index_space(&alphabet()) * std::mem::size_of::<u32>() as u64;
+    (guesses * answers) as u64 + lookups
+}
+
+// Precompute the table for these pools, replacing any previous one. If the full
+// table would take more than `max_bytes`, fall back to rows for just the answers
+// (the guesses that matter most in the endgame), and failing that to no table;
+// anything left out is scored on the fly as before.
+pub fn precompute_table(guesses: &[Word], answers: &[Word], max_bytes: u64) {
+    let mb = |bytes: u64| bytes >> 20;
+    let full = table_bytes(guesses.len(), answers.len());
+    let rows = if full <= max_bytes {
+        Some(guesses)
+    } else if table_bytes(answers.len(), answers.len()) <= max_bytes {
+        eprintln!(
+            "Full bucket table needs ~{} MB, over the {} MB budget; precomputing answer rows only",
+            mb(full),
+            mb(max_bytes)
+        );
+        Some(answers)
+    } else {
+        eprintln!(
+            "Bucket table needs ~{} MB, over the {} MB budget; scoring on the fly",
+            mb(full),
+            mb(max_bytes)
+        );
+        None
+    };
+
+    let table = rows.and_then(|rows| BucketTable::new(rows, answers));
+    *TABLE.write().unwrap() = table;
+}
+
+// bucket_counts for many patterns against the same answers. Answers are looked up
+// in the table once, so each pattern costs one row scan when the table covers it.
+pub struct BucketCounter<'a> {
+    answers: &'a Vec<Word>,
+    table: RwLockReadGuard<'static, Option<BucketTable>>,
+    columns: Option<Vec<usize>>,
+}
+
+impl<'a> BucketCounter<'a> {
+    pub fn new(answers: &'a Vec<Word>) -> BucketCounter<'a> {
+        let table = TABLE.read().unwrap();
+        let columns = table
+            .as_ref()
+            .filter(|table| table.is_current())
+            .and_then(|table| {
+                answers
+                    .iter()
+                    .map(|answer| table.position(&table.columns, answer))
+                    .collect()
+            });
+        BucketCounter {
+            answers,
+            table,
+            columns,
+        }
+    }
+
+    pub fn counts(&self, pattern: Word) -> Buckets<usize> {
+        if let (Some(table), Some(columns)) = (self.table.as_ref(), &self.columns) {
+            if let Some(row) = table.position(&table.rows, &pattern) {
+                let row = &table.buckets[row * table.num_answers..(row + 1) * table.num_answers];
+                let mut counts = Buckets::new();
+                for &column in columns {
+                    counts[row[column] as usize] += 1;
+                }
+                return counts;
+            }
+        }
+        bucket_counts(self.answers, pattern)
+    }
+}
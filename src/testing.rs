@@ -0,0 +1,63 @@
+// Helpers for tests that need small, controlled dictionaries.
+
+use crate::word::{read_words, string_to_word, Word};
+
+// A dictionary of lowercase words, e.g. words(&["crane", "crate", "slate"]).
+pub fn words(words: &[&str]) -> Vec<Word> {
+    words
+        .iter()
+        .map(|word| string_to_word(word).unwrap())
+        .collect()
+}
+
+// The path to one of the bundled dictionaries, e.g. dictionary_path("wordle.txt").
+pub fn dictionary_path(name: &str) -> String {
+    format!("{}/dictionaries/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+// The path to one of the dictionaries in dictionaries/fixtures, for tests that
+// read it some other way than `fixture`.
+pub fn fixture_path(name: &str) -> String {
+    dictionary_path(&format!("fixtures/{}", name))
+}
+
+// One of the dictionaries in dictionaries/fixtures, by file name.
+pub fn fixture(name: &str) -> Vec<Word> {
+    read_words(&fixture_path(name), "#", false).unwrap()
+}
+
+pub fn word(word: &str) -> Word {
+    words(&[word])[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bucket::{bucket_counts, partition_count};
+
+    #[test]
+    fn words_builds_each_word_in_order() {
+        let built = words(&["crane", "crate", "slate"]);
+        assert_eq!(
+            built,
+            vec![
+                ['c', 'r', 'a', 'n', 'e'],
+                ['c', 'r', 'a', 't', 'e'],
+                ['s', 'l', 'a', 't', 'e'],
+            ]
+        );
+    }
+
+    #[test]
+    fn split_fixture_has_a_known_distribution() {
+        let answers = fixture("split.txt");
+        let mut sizes: Vec<usize> = bucket_counts(&answers, word("crane"))
+            .values()
+            .copied()
+            .filter(|&count| count > 0)
+            .collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![1, 1, 1, 1, 1, 2]);
+        assert_eq!(partition_count(&answers, word("crane")), 6);
+    }
+}
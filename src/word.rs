@@ -0,0 +1,389 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::sync::RwLock;
+
+pub const WORD_LEN: usize = 5;
+
+pub type Word = [char; WORD_LEN]; // Stack-allocated fixed-size word for cache efficiency
+
+// Optimal first word is always the same
+pub const FIRST_GUESS: Word = ['r', 'a', 'i', 's', 'e'];
+
+pub fn string_to_word(s: &str) -> Result<Word, String> {
+    let letters = s.chars().count();
+    if letters != WORD_LEN {
+        return Err(format!("expected {} letters, got {}", WORD_LEN, letters));
+    }
+
+    let mut word: Word = Default::default();
+    for (i, c) in s.chars().enumerate() {
+        word[i] = c;
+    }
+    Ok(word)
+}
+
+pub fn word_to_string(word: &Word) -> String {
+    word.iter().collect()
+}
+
+pub const DEFAULT_DICTIONARY: &str = "dictionaries/wordle.txt";
+
+pub const DEFAULT_COMMENT_PREFIX: &str = "#";
+
+// Where a dictionary comes from: `-` is stdin, an http(s) URL is fetched (with the
+// `http` feature), and anything else is a file path.
+pub enum DictSource<'a> {
+    File(&'a str),
+    Stdin,
+    Url(&'a str),
+}
+
+impl<'a> DictSource<'a> {
+    pub fn from_arg(arg: &'a str) -> DictSource<'a> {
+        if arg == "-" {
+            DictSource::Stdin
+        } else if arg.starts_with("http://") || arg.starts_with("https://") {
+            DictSource::Url(arg)
+        } else {
+            DictSource::File(arg)
+        }
+    }
+
+    pub fn open(&self) -> Result<Box<dyn BufRead>, String> {
+        Ok(match self {
+            DictSource::File(path) => Box::new(io::BufReader::new(
+                File::open(path).map_err(|error| format!("{}: {}", path, error))?,
+            )),
+            DictSource::Stdin => Box::new(io::BufReader::new(io::stdin())),
+            DictSource::Url(url) => Box::new(io::Cursor::new(fetch(url)?)),
+        })
+    }
+}
+
+// Shell out to curl rather than pulling in an HTTP client, keeping the build lean
+// even with the feature on.
+#[cfg(feature = "http")]
+fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    let output = std::process::Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", url])
+        .output()
+        .map_err(|error| format!("Couldn't run curl to fetch {}: {}", url, error))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Fetching {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    Err(format!(
+        "Can't fetch {}: rebuild with `--features http`",
+        url
+    ))
+}
+
+pub fn read_words(
+    source: &str,
+    comment_prefix: &str,
+    allow_punctuation: bool,
+) -> Result<Vec<Word>, String> {
+    read_words_from(
+        DictSource::from_arg(source).open()?,
+        comment_prefix,
+        allow_punctuation,
+    )
+}
+
+// Blank lines and lines starting with `comment_prefix` (unless it's empty) are
+// skipped, so annotated word lists can be used as-is. Words with anything but letters ("don't", "x-ray")
+// are skipped with a warning unless `allow_punctuation`, which makes the extra
+// symbols part of the alphabet like any letter.
+pub fn read_words_from(
+    reader: impl BufRead,
+    comment_prefix: &str,
+    allow_punctuation: bool,
+) -> Result<Vec<Word>, String> {
+    let (words, skipped) = parse_words(reader, comment_prefix, allow_punctuation)?;
+    if let Some(example) = skipped.first() {
+        eprintln!(
+            "Skipped {} words with non-letter characters (e.g. {}); pass --allow-punctuation to keep them",
+            skipped.len(),
+            example
+        );
+    }
+    learn_alphabet(&words)?;
+    Ok(words)
+}
+
+// The words in a dictionary, and the lines skipped for having non-letters.
+fn parse_words(
+    reader: impl BufRead,
+    comment_prefix: &str,
+    allow_punctuation: bool,
+) -> Result<(Vec<Word>, Vec<String>), String> {
+    let mut words = Vec::new();
+    let mut skipped = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|error| error.to_string())?;
+        let line = line.trim();
+        let comment = !comment_prefix.is_empty() && line.starts_with(comment_prefix);
+        if line.is_empty() || comment {
+            continue;
+        }
+        if !allow_punctuation && !line.chars().all(char::is_alphabetic) {
+            skipped.push(line.to_string());
+            continue;
+        }
+        let word = string_to_word(line)
+            .map_err(|message| format!("Invalid dictionary line: {}: {}", line, message))?;
+        words.push(word);
+    }
+    Ok((words, skipped))
+}
+
+// Every character seen in any loaded dictionary, sorted. get_bucket counts letters
+// in an array indexed by position in this alphabet, so any fixed alphabet works
+// (Cyrillic, accented Latin, etc.), not just a-z.
+pub(crate) const MAX_ALPHABET: usize = 128;
+pub(crate) static ALPHABET: RwLock<Vec<char>> = RwLock::new(Vec::new());
+
+// Add the words' characters to the alphabet. Leaves it as it was if that would make
+// it too large.
+pub(crate) fn learn_alphabet(words: &[Word]) -> Result<(), String> {
+    let mut alphabet = ALPHABET.write().unwrap();
+    let mut grown = alphabet.clone();
+    for &c in words.iter().flatten() {
+        if let Err(i) = grown.binary_search(&c) {
+            grown.insert(i, c);
+        }
+    }
+
+    if grown.len() > MAX_ALPHABET {
+        return Err(format!(
+            "Dictionaries use {} distinct characters; at most {} are supported",
+            grown.len(),
+            MAX_ALPHABET
+        ));
+    }
+    *alphabet = grown;
+    Ok(())
+}
+
+// Every character any loaded dictionary uses, sorted.
+pub fn alphabet() -> Vec<char> {
+    ALPHABET.read().unwrap().clone()
+}
+
+// Words as integers: the base-(alphabet size) number whose digits are each letter's
+// position in `alphabet`, first letter most significant. This is a bijection
+// between the words it can spell and 0..index_space(alphabet), so words can index
+// dense arrays. None if the word has a character outside the alphabet.
+pub fn word_to_index(alphabet: &[char], word: &Word) -> Option<u64> {
+    word.iter().try_fold(0, |index, c| {
+        let digit = alphabet.binary_search(c).ok()?;
+        Some(index * alphabet.len() as u64 + digit as u64)
+    })
+}
+
+// Panics if `index` is outside 0..index_space(alphabet).
+pub fn index_to_word(alphabet: &[char], mut index: u64) -> Word {
+    let base = alphabet.len() as u64;
+    assert!(
+        index < base.pow(WORD_LEN as u32),
+        "word index {} is out of range for a {}-symbol alphabet",
+        index,
+        base
+    );
+    let mut word: Word = Default::default();
+    for c in word.iter_mut().rev() {
+        *c = alphabet[(index % base) as usize];
+        index /= base;
+    }
+    word
+}
+
+// How many distinct words the alphabet can spell.
+pub fn index_space(alphabet: &[char]) -> u64 {
+    (alphabet.len() as u64).pow(WORD_LEN as u32)
+}
+
+// The first character of a word that isn't in the learned alphabet, if any.
+pub fn unknown_char(word: &Word) -> Option<char> {
+    let alphabet = ALPHABET.read().unwrap();
+    word.iter()
+        .copied()
+        .find(|c| alphabet.binary_search(c).is_err())
+}
+
+// How the answer pool relates to the guess pool.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PoolMode {
+    // Every answer is also a valid guess: the guess pool is extended with the answers.
+    #[default]
+    Subset,
+    // Any valid guess can be the answer (e.g. some Absurdle configs).
+    Equal,
+    // Use both lists exactly as given.
+    Custom,
+}
+
+impl PoolMode {
+    pub fn from_name(name: &str) -> Option<PoolMode> {
+        match name {
+            "subset" => Some(PoolMode::Subset),
+            "equal" => Some(PoolMode::Equal),
+            "custom" => Some(PoolMode::Custom),
+            _ => None,
+        }
+    }
+}
+
+// Load the (answers, guesses) pools from their dictionaries according to `mode`.
+pub fn read_pools(
+    answers_path: &str,
+    guesses_path: &str,
+    mode: PoolMode,
+    comment_prefix: &str,
+    allow_punctuation: bool,
+) -> Result<(Vec<Word>, Vec<Word>), String> {
+    let mut guesses = read_words(guesses_path, comment_prefix, allow_punctuation)?;
+    let answers = match mode {
+        PoolMode::Equal => guesses.clone(),
+        // Don't read the same source twice; stdin can only be read once anyway.
+        PoolMode::Subset | PoolMode::Custom if answers_path == guesses_path => guesses.clone(),
+        PoolMode::Subset | PoolMode::Custom => {
+            read_words(answers_path, comment_prefix, allow_punctuation)?
+        }
+    };
+
+    if mode == PoolMode::Subset {
+        let known: HashSet<Word> = guesses.iter().copied().collect();
+        guesses.extend(answers.iter().filter(|answer| !known.contains(*answer)));
+    }
+
+    Ok((answers, guesses))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bucket::{bucket_to_pattern, get_bucket};
+    use crate::testing::{fixture, fixture_path, word, words};
+    use std::io::Cursor;
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        assert_eq!(
+            fixture("commented.txt"),
+            words(&["crane", "slate", "trace", "crate"])
+        );
+    }
+
+    #[test]
+    fn comment_prefix_is_configurable() {
+        let list = "// a comment\ncrane\n//slate\n";
+        let read = read_words_from(Cursor::new(list), "//", false).unwrap();
+        assert_eq!(read, words(&["crane"]));
+    }
+
+    #[test]
+    fn words_with_punctuation_are_dropped_and_counted() {
+        let file = File::open(fixture_path("punctuated.txt")).unwrap();
+        let (read, skipped) = parse_words(io::BufReader::new(file), "#", false).unwrap();
+        assert_eq!(read, words(&["crane", "slate", "trace", "grace"]));
+        assert_eq!(skipped, vec!["don't", "x-ray", "ma'am", "won't"]);
+    }
+
+    #[test]
+    fn allow_punctuation_keeps_symbols_as_letters() {
+        let read = read_words(&fixture_path("punctuated.txt"), "#", true).unwrap();
+        assert_eq!(read.len(), 8);
+        assert!(read.contains(&['d', 'o', 'n', '\'', 't']));
+        assert!(read.contains(&['x', '-', 'r', 'a', 'y']));
+        assert!(alphabet().contains(&'\''));
+        assert!(alphabet().contains(&'-'));
+
+        // Symbols score like any letter
+        let pattern = get_bucket(word("don't"), word("won't"));
+        assert_eq!(bucket_to_pattern(pattern), ".++++");
+    }
+
+    #[test]
+    fn oversized_alphabets_are_an_error() {
+        let symbols: Vec<char> = ('Ā'..)
+            .filter(|c| c.is_alphabetic())
+            .take(MAX_ALPHABET)
+            .collect();
+        let huge: Vec<Word> = symbols
+            .chunks(WORD_LEN)
+            .filter(|chunk| chunk.len() == WORD_LEN)
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect();
+        assert!(learn_alphabet(&huge).is_err());
+        assert!(!alphabet().iter().any(|c| symbols.contains(c)));
+    }
+
+    #[test]
+    fn empty_comment_prefix_means_no_comments() {
+        let read = read_words_from(Cursor::new("crane\n\nslate\n"), "", false).unwrap();
+        assert_eq!(read, words(&["crane", "slate"]));
+    }
+
+    #[test]
+    fn string_to_word_takes_exactly_word_len_letters() {
+        assert_eq!(string_to_word("crane"), Ok(['c', 'r', 'a', 'n', 'e']));
+        assert_eq!(
+            string_to_word("abc"),
+            Err("expected 5 letters, got 3".to_string())
+        );
+        assert_eq!(
+            string_to_word("toolong"),
+            Err("expected 5 letters, got 7".to_string())
+        );
+        assert!(string_to_word("").is_err());
+        // Letters, not bytes
+        assert!(string_to_word("ёжики").is_ok());
+    }
+
+    fn letters() -> Vec<char> {
+        ('a'..='z').collect()
+    }
+
+    #[test]
+    fn word_indices_round_trip() {
+        let letters = letters();
+        for word in words(&["crane", "geese", "abbey", "pixie"]) {
+            let index = word_to_index(&letters, &word).unwrap();
+            assert_eq!(index_to_word(&letters, index), word);
+        }
+    }
+
+    #[test]
+    fn index_space_ends_round_trip() {
+        let letters = letters();
+        let first = word("aaaaa");
+        let last = word("zzzzz");
+        let space = index_space(&letters);
+        assert_eq!(word_to_index(&letters, &first), Some(0));
+        assert_eq!(word_to_index(&letters, &last), Some(space - 1));
+        assert_eq!(index_to_word(&letters, 0), first);
+        assert_eq!(index_to_word(&letters, space - 1), last);
+    }
+
+    #[test]
+    fn words_outside_the_alphabet_have_no_index() {
+        assert_eq!(word_to_index(&letters(), &word("don't")), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn index_past_the_space_panics() {
+        let letters = letters();
+        index_to_word(&letters, index_space(&letters));
+    }
+}
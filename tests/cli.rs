@@ -0,0 +1,147 @@
+// End-to-end checks of the wordle-bot binary on fixture dictionaries.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wordle-bot"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn run_with_input(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_wordle-bot"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn empty_dictionary_exits_with_an_error() {
+    let output = run(&["--dict", "dictionaries/fixtures/empty.txt"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("dictionary is empty or contains no valid words"));
+}
+
+#[test]
+fn single_word_dictionary_reports_the_answer() {
+    let output = run(&["--dict", "dictionaries/fixtures/single.txt"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "Only possible answer: crane\n");
+}
+
+#[test]
+fn single_word_dictionary_prints_just_the_guess_for_bots() {
+    let output = run(&[
+        "--dict",
+        "dictionaries/fixtures/single.txt",
+        "--print-guess-only",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "crane\n");
+}
+
+#[test]
+fn input_ending_early_lists_candidates_without_ranking_them() {
+    let output = run(&[]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Out of input with 12972 candidates left:\n"));
+}
+
+#[test]
+fn bad_arguments_exit_with_an_error_not_a_panic() {
+    let output = run(&["--hardest", "x", "--exit-code-count"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr, "--hardest must be an integer\n");
+}
+
+#[test]
+fn exit_code_count_keeps_counts_apart_from_errors() {
+    let code = |result| {
+        let dict = "dictionaries/fixtures/split.txt";
+        let args = ["--dict", dict, "--guess", "crane", "--result", result];
+        run(&[&args[..], &["--exit-code-count"]].concat())
+            .status
+            .code()
+    };
+    assert_eq!(code("+++++"), Some(0));
+    assert_eq!(code("....."), Some(2));
+    assert_eq!(code("...+."), Some(3));
+}
+
+#[test]
+fn reloading_picks_an_opener_for_the_new_pools() {
+    let output = run_with_input(
+        &["--dict", "dictionaries/fixtures/split.txt"],
+        "reload dictionaries/fixtures/cluster.txt\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (before, after) = stdout.split_once("Reloaded").unwrap();
+    assert!(before.contains("Enter pattern: crate\n"));
+    assert!(after.contains("Enter pattern: batch\n"));
+}
+
+#[test]
+fn input_ending_while_confirming_lists_nothing_more() {
+    let output = run_with_input(
+        &["--dict", "dictionaries/fixtures/split.txt", "--confirm"],
+        ".....\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Found word: pound\n"));
+    assert!(!stdout.contains("Out of input"));
+}
+
+#[test]
+fn alpha_must_be_between_zero_and_one() {
+    for alpha in ["nan", "1.5", "-0.1", "inf"] {
+        let output = run(&["--alpha", alpha]);
+        assert_eq!(output.status.code(), Some(1), "{}", alpha);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(stderr, "--alpha must be a number from 0 to 1\n");
+    }
+}
+
+#[test]
+fn unreadable_archives_exit_with_an_error() {
+    let dict = "dictionaries/fixtures/split.txt";
+    let output = run(&["--dict", dict, "--archive", "missing.tsv"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing.tsv: "));
+
+    // Not tab-separated
+    let output = run(&["--dict", dict, "--archive", dict]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid archive line: crane\n"));
+}
+
+#[test]
+fn missing_batch_files_exit_with_an_error() {
+    let dict = "dictionaries/fixtures/split.txt";
+    let output = run(&["--dict", dict, "--batch", "missing.txt"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing.txt: "));
+}